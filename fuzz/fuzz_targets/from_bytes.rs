@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(bntx) = bntx::Bntx::from_bytes(data) {
+        for texture in bntx.textures() {
+            let _ = texture.to_surface();
+            let _ = texture.to_dds();
+            let _ = texture.to_surface_rgba8(true);
+            let _ = texture.deswizzled_data_strict();
+            let _ = texture.mip(0, 0);
+            let _ = texture.mip(
+                texture.layer_count().saturating_sub(1),
+                texture.mipmap_count().saturating_sub(1),
+            );
+        }
+        let _ = bntx.to_bytes();
+    }
+});
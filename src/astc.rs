@@ -0,0 +1,1152 @@
+//! Software decoding for ASTC compressed textures.
+//!
+//! image_dds does not support ASTC, so blocks are decoded here following the
+//! Khronos Data Format Specification. LDR blocks decode to 8-bit RGBA.
+//! HDR blocks decode to RGBA half floats.
+
+const BLOCK_SIZE: usize = 16;
+
+/// The color for blocks with invalid or unsupported encodings.
+const ERROR_COLOR: [u8; 4] = [255, 0, 255, 255];
+
+/// Returns `true` if any block in `data` uses HDR endpoints or an HDR void-extent color.
+pub(crate) fn is_hdr(data: &[u8], block_width: u32, block_height: u32) -> bool {
+    data.chunks_exact(BLOCK_SIZE).any(|block| {
+        let bits = block_bits(block);
+        if is_void_extent(bits) {
+            (bits >> 9) & 1 == 1
+        } else {
+            BlockHeader::read(bits, block_width, block_height)
+                .map(|h| h.cems[..h.partition_count].iter().any(|c| is_hdr_cem(*c)))
+                .unwrap_or_default()
+        }
+    })
+}
+
+/// Decode a single image of `width` x `height` x `depth` pixels.
+///
+/// The output uses 4 bytes per pixel or 8 bytes per pixel with half floats if `hdr` is `true`.
+/// sRGB data stays sRGB encoded.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn decode(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    depth: u32,
+    block_width: u32,
+    block_height: u32,
+    srgb: bool,
+    hdr: bool,
+) -> Vec<u8> {
+    let bytes_per_pixel = if hdr { 8 } else { 4 };
+    let width = width as usize;
+    let height = height as usize;
+    let block_width = block_width as usize;
+    let block_height = block_height as usize;
+    let blocks_x = width.div_ceil(block_width);
+    let blocks_y = height.div_ceil(block_height);
+
+    let mut output = vec![0u8; width * height * depth as usize * bytes_per_pixel];
+    let mut blocks = data.chunks_exact(BLOCK_SIZE);
+    for z in 0..depth as usize {
+        for block_y in 0..blocks_y {
+            for block_x in 0..blocks_x {
+                let texels = match blocks.next() {
+                    Some(block) => decode_block(
+                        block_bits(block),
+                        block_width as u32,
+                        block_height as u32,
+                        srgb,
+                        hdr,
+                    ),
+                    None => error_block(block_width * block_height, hdr),
+                };
+
+                for (i, texel) in texels.iter().enumerate() {
+                    let x = block_x * block_width + i % block_width;
+                    let y = block_y * block_height + i / block_width;
+                    if x < width && y < height {
+                        let offset = ((z * height + y) * width + x) * bytes_per_pixel;
+                        let pixel = &mut output[offset..offset + bytes_per_pixel];
+                        if hdr {
+                            for (c, value) in texel.iter().enumerate() {
+                                pixel[c * 2..c * 2 + 2].copy_from_slice(&value.to_le_bytes());
+                            }
+                        } else {
+                            for (c, value) in texel.iter().enumerate() {
+                                pixel[c] = *value as u8;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    output
+}
+
+fn block_bits(block: &[u8]) -> u128 {
+    let mut bytes = [0u8; BLOCK_SIZE];
+    bytes.copy_from_slice(block);
+    u128::from_le_bytes(bytes)
+}
+
+fn is_void_extent(bits: u128) -> bool {
+    bits & 0x1FF == 0x1FC
+}
+
+fn is_hdr_cem(cem: u32) -> bool {
+    matches!(cem, 2 | 3 | 7 | 11 | 14 | 15)
+}
+
+/// Texel colors as 8-bit values for LDR or half float bits for HDR.
+type Texel = [u16; 4];
+
+fn error_block(texel_count: usize, hdr: bool) -> Vec<Texel> {
+    let texel = if hdr {
+        ERROR_COLOR.map(|c| unorm16_to_f16(c as u32 * 257))
+    } else {
+        ERROR_COLOR.map(u16::from)
+    };
+    vec![texel; texel_count]
+}
+
+fn decode_block(
+    bits: u128,
+    block_width: u32,
+    block_height: u32,
+    srgb: bool,
+    hdr: bool,
+) -> Vec<Texel> {
+    let texel_count = (block_width * block_height) as usize;
+    if is_void_extent(bits) {
+        return decode_void_extent(bits, texel_count, srgb, hdr);
+    }
+
+    decode_partitioned_block(bits, block_width, block_height, srgb, hdr)
+        .unwrap_or_else(|| error_block(texel_count, hdr))
+}
+
+fn decode_void_extent(bits: u128, texel_count: usize, srgb: bool, hdr: bool) -> Vec<Texel> {
+    let color: [u16; 4] = std::array::from_fn(|i| (bits >> (64 + i * 16)) as u16);
+    let texel = match ((bits >> 9) & 1 == 1, hdr) {
+        // The color is stored as half floats.
+        (true, true) if !srgb => color,
+        (true, _) => return error_block(texel_count, hdr),
+        (false, true) => color.map(|c| unorm16_to_f16(c as u32)),
+        (false, false) => color.map(|c| c >> 8),
+    };
+    vec![texel; texel_count]
+}
+
+fn decode_partitioned_block(
+    bits: u128,
+    block_width: u32,
+    block_height: u32,
+    srgb: bool,
+    hdr: bool,
+) -> Option<Vec<Texel>> {
+    let header = BlockHeader::read(bits, block_width, block_height)?;
+
+    // Color endpoints use the largest range that fits in the remaining bits.
+    let value_count: usize = header.cems[..header.partition_count]
+        .iter()
+        .map(|c| endpoint_value_count(*c))
+        .sum();
+    if value_count > 18 {
+        return None;
+    }
+    let available_bits = header.color_end.checked_sub(header.color_start)?;
+    let color_quant = (0..QUANT_LEVELS.len())
+        .rev()
+        .find(|i| QUANT_LEVELS[*i].bit_count(value_count) <= available_bits)
+        .filter(|i| QUANT_LEVELS[*i].levels >= 6)
+        .map(|i| QUANT_LEVELS[i])?;
+
+    let mut reader = BitReader::new(bits, header.color_start, color_quant.bit_count(value_count));
+    let values: Vec<_> = decode_ise(&mut reader, value_count, color_quant)
+        .into_iter()
+        .map(|(m, d)| color_quant.unquantize_color(m, d))
+        .collect();
+
+    let mut endpoints = Vec::new();
+    let mut remaining = &values[..];
+    for cem in &header.cems[..header.partition_count] {
+        let (partition_values, rest) = remaining.split_at(endpoint_value_count(*cem));
+        remaining = rest;
+        let e = Endpoints::decode(*cem, partition_values, srgb);
+        if e.hdr_rgb && (srgb || !hdr) {
+            return None;
+        }
+        endpoints.push(e);
+    }
+
+    // Weights are stored in reverse starting from the most significant bit.
+    let mode = &header.mode;
+    let plane_count = if mode.dual_plane { 2 } else { 1 };
+    let weight_count = (mode.grid_width * mode.grid_height) as usize * plane_count;
+    let mut reader = BitReader::new(bits.reverse_bits(), 0, header.weight_bits);
+    let weights: Vec<_> = decode_ise(&mut reader, weight_count, mode.weight_quant)
+        .into_iter()
+        .map(|(m, d)| mode.weight_quant.unquantize_weight(m, d))
+        .collect();
+    let plane_weights: Vec<_> = (0..plane_count)
+        .map(|p| {
+            let grid: Vec<_> = weights
+                .iter()
+                .skip(p)
+                .step_by(plane_count)
+                .copied()
+                .collect();
+            infill_weights(&grid, mode, block_width, block_height)
+        })
+        .collect();
+
+    let small_block = block_width * block_height < 31;
+    let texels = (0..block_height)
+        .flat_map(|y| (0..block_width).map(move |x| (x, y)))
+        .enumerate()
+        .map(|(i, (x, y))| {
+            let partition = if header.partition_count > 1 {
+                select_partition(
+                    header.seed,
+                    x,
+                    y,
+                    header.partition_count as u32,
+                    small_block,
+                )
+            } else {
+                0
+            };
+            let e = &endpoints[partition];
+            std::array::from_fn(|c| {
+                let plane = if mode.dual_plane && c == header.plane2_component {
+                    1
+                } else {
+                    0
+                };
+                let w = plane_weights[plane][i];
+                let value = (e.low[c] * (64 - w) + e.high[c] * w + 32) >> 6;
+                if e.is_hdr(c) {
+                    lns_to_f16(value)
+                } else if hdr {
+                    unorm16_to_f16(value)
+                } else {
+                    (value >> 8) as u16
+                }
+            })
+        })
+        .collect();
+    Some(texels)
+}
+
+/// The block mode, partitioning, and endpoint modes before the color and weight data.
+struct BlockHeader {
+    mode: BlockMode,
+    partition_count: usize,
+    seed: u32,
+    cems: [u32; 4],
+    color_start: u32,
+    color_end: u32,
+    plane2_component: usize,
+    weight_bits: u32,
+}
+
+impl BlockHeader {
+    fn read(bits: u128, block_width: u32, block_height: u32) -> Option<Self> {
+        let mode = BlockMode::read((bits & 0x7FF) as u32)?;
+        if mode.grid_width > block_width || mode.grid_height > block_height {
+            return None;
+        }
+
+        let plane_count = if mode.dual_plane { 2 } else { 1 };
+        let weight_count = mode.grid_width * mode.grid_height * plane_count;
+        let weight_bits = mode.weight_quant.bit_count(weight_count as usize);
+        if weight_count > 64 || !(24..=96).contains(&weight_bits) {
+            return None;
+        }
+
+        let partition_count = ((bits >> 11) & 3) as usize + 1;
+        if mode.dual_plane && partition_count == 4 {
+            return None;
+        }
+
+        let mut cems = [0; 4];
+        let mut extra_cem_bits = 0;
+        let (seed, color_start) = if partition_count == 1 {
+            cems[0] = ((bits >> 13) & 0xF) as u32;
+            (0, 17)
+        } else {
+            let cem = ((bits >> 23) & 0x3F) as u32;
+            if cem & 3 == 0 {
+                // All partitions use the same mode.
+                cems = [cem >> 2; 4];
+            } else {
+                // Each partition has a class relative to the base class and a mode.
+                // Bits that don't fit in the header are stored below the weights.
+                extra_cem_bits = 3 * partition_count as u32 - 4;
+                let extra_start = 128 - weight_bits - extra_cem_bits;
+                let extra = (bits >> extra_start) as u32 & ((1 << extra_cem_bits) - 1);
+                let value = (cem >> 2) | (extra << 4);
+                let base = (cem & 3) - 1;
+                for (i, c) in cems[..partition_count].iter_mut().enumerate() {
+                    let class = base + ((value >> i) & 1);
+                    let m = (value >> (partition_count + i * 2)) & 3;
+                    *c = (class << 2) | m;
+                }
+            }
+            (((bits >> 13) & 0x3FF) as u32, 29)
+        };
+
+        let mut color_end = 128 - weight_bits - extra_cem_bits;
+        let mut plane2_component = 0;
+        if mode.dual_plane {
+            color_end -= 2;
+            plane2_component = ((bits >> color_end) & 3) as usize;
+        }
+
+        Some(Self {
+            mode,
+            partition_count,
+            seed,
+            cems,
+            color_start,
+            color_end,
+            plane2_component,
+            weight_bits,
+        })
+    }
+}
+
+struct BlockMode {
+    grid_width: u32,
+    grid_height: u32,
+    dual_plane: bool,
+    weight_quant: Quant,
+}
+
+impl BlockMode {
+    fn read(mode: u32) -> Option<Self> {
+        let bit = |i: u32| (mode >> i) & 1;
+        let a = (mode >> 5) & 3;
+
+        let mut high_precision = bit(9);
+        let mut dual_plane = bit(10);
+        let (range, grid_width, grid_height) = if mode & 3 != 0 {
+            let b = (mode >> 7) & 3;
+            let (w, h) = match (mode >> 2) & 3 {
+                0 => (b + 4, a + 2),
+                1 => (b + 8, a + 2),
+                2 => (a + 2, b + 8),
+                _ if bit(8) == 0 => (a + 2, bit(7) + 6),
+                _ => (bit(7) + 2, a + 2),
+            };
+            (bit(4) | ((mode & 3) << 1), w, h)
+        } else {
+            if mode & 0xF == 0 {
+                return None;
+            }
+            let (w, h) = match (mode >> 7) & 3 {
+                0 => (12, a + 2),
+                1 => (a + 2, 12),
+                2 => {
+                    let b = (mode >> 9) & 3;
+                    high_precision = 0;
+                    dual_plane = 0;
+                    (a + 6, b + 6)
+                }
+                _ => match a {
+                    0 => (6, 10),
+                    1 => (10, 6),
+                    _ => return None,
+                },
+            };
+            (bit(4) | (((mode >> 2) & 3) << 1), w, h)
+        };
+
+        Some(Self {
+            grid_width,
+            grid_height,
+            dual_plane: dual_plane == 1,
+            weight_quant: QUANT_LEVELS[(range - 2 + high_precision * 6) as usize],
+        })
+    }
+}
+
+/// A quantization range encoded with an optional trit or quint and some number of bits.
+#[derive(Clone, Copy)]
+struct Quant {
+    levels: u32,
+    trits: bool,
+    quints: bool,
+    bits: u32,
+}
+
+const fn quant(levels: u32, trits: bool, quints: bool, bits: u32) -> Quant {
+    Quant {
+        levels,
+        trits,
+        quints,
+        bits,
+    }
+}
+
+/// Ranges in increasing order from 2 to 256 values.
+/// Weights use the first 12 ranges.
+const QUANT_LEVELS: [Quant; 21] = [
+    quant(2, false, false, 1),
+    quant(3, true, false, 0),
+    quant(4, false, false, 2),
+    quant(5, false, true, 0),
+    quant(6, true, false, 1),
+    quant(8, false, false, 3),
+    quant(10, false, true, 1),
+    quant(12, true, false, 2),
+    quant(16, false, false, 4),
+    quant(20, false, true, 2),
+    quant(24, true, false, 3),
+    quant(32, false, false, 5),
+    quant(40, false, true, 3),
+    quant(48, true, false, 4),
+    quant(64, false, false, 6),
+    quant(80, false, true, 4),
+    quant(96, true, false, 5),
+    quant(128, false, false, 7),
+    quant(160, false, true, 5),
+    quant(192, true, false, 6),
+    quant(256, false, false, 8),
+];
+
+impl Quant {
+    /// The number of bits used to encode `count` values.
+    fn bit_count(&self, count: usize) -> u32 {
+        let count = count as u32;
+        let extra = if self.trits {
+            (8 * count).div_ceil(5)
+        } else if self.quints {
+            (7 * count).div_ceil(3)
+        } else {
+            0
+        };
+        count * self.bits + extra
+    }
+
+    /// Unquantize the bits `m` and trit or quint `d` to the range 0 to 64.
+    fn unquantize_weight(&self, m: u32, d: u32) -> u32 {
+        if self.bits == 0 {
+            // Trits and quints without bits are evenly spaced.
+            return d * if self.trits { 32 } else { 16 };
+        }
+
+        let value = if !self.trits && !self.quints {
+            replicate(m, self.bits, 6)
+        } else {
+            let bit = |i: u32| (m >> i) & 1;
+            let (b, c) = match (self.trits, self.bits) {
+                (true, 1) => (0, 50),
+                (true, 2) => (bit(1) * 0x45, 23),
+                (true, _) => (bit(2) * 0x42 + bit(1) * 0x21, 11),
+                (false, 1) => (0, 28),
+                (false, _) => (bit(1) * 0x42, 13),
+            };
+            let a = if m & 1 == 1 { 0x7F } else { 0 };
+            let t = (d * c + b) ^ a;
+            (a & 0x20) | (t >> 2)
+        };
+        if value > 32 {
+            value + 1
+        } else {
+            value
+        }
+    }
+
+    /// Unquantize the bits `m` and trit or quint `d` to the range 0 to 255.
+    fn unquantize_color(&self, m: u32, d: u32) -> u32 {
+        if !self.trits && !self.quints {
+            return replicate(m, self.bits, 8);
+        }
+
+        let bit = |i: u32| (m >> i) & 1;
+        let (b, c) = if self.trits {
+            match self.bits {
+                1 => (0, 204),
+                2 => (bit(1) * 0x116, 93),
+                3 => (bit(2) * 0x10A + bit(1) * 0x85, 44),
+                4 => (bit(3) * 0x104 + bit(2) * 0x82 + bit(1) * 0x41, 22),
+                5 => (
+                    bit(4) * 0x102 + bit(3) * 0x81 + bit(2) * 0x40 + bit(1) * 0x20,
+                    11,
+                ),
+                _ => (
+                    bit(5) * 0x101 + bit(4) * 0x80 + bit(3) * 0x40 + bit(2) * 0x20 + bit(1) * 0x10,
+                    5,
+                ),
+            }
+        } else {
+            match self.bits {
+                1 => (0, 113),
+                2 => (bit(1) * 0x10C, 54),
+                3 => (bit(2) * 0x105 + bit(1) * 0x82, 26),
+                4 => (bit(3) * 0x102 + bit(2) * 0x81 + bit(1) * 0x40, 13),
+                _ => (
+                    bit(4) * 0x101 + bit(3) * 0x80 + bit(2) * 0x40 + bit(1) * 0x20,
+                    6,
+                ),
+            }
+        };
+        let a = if m & 1 == 1 { 0x1FF } else { 0 };
+        let t = (d * c + b) ^ a;
+        (a & 0x80) | (t >> 2)
+    }
+}
+
+/// Repeat the `bits` lowest bits of `value` to fill `target_bits` bits.
+fn replicate(value: u32, bits: u32, target_bits: u32) -> u32 {
+    let mut result = 0;
+    let mut filled = 0;
+    while filled < target_bits {
+        result = (result << bits) | value;
+        filled += bits;
+    }
+    result >> (filled - target_bits)
+}
+
+/// Reads bits in increasing order and treats bits past the end as zero.
+struct BitReader {
+    bits: u128,
+    position: u32,
+    end: u32,
+}
+
+impl BitReader {
+    fn new(bits: u128, start: u32, count: u32) -> Self {
+        Self {
+            bits,
+            position: start,
+            end: start + count,
+        }
+    }
+
+    fn read(&mut self, count: u32) -> u32 {
+        let available = self.end.saturating_sub(self.position).min(count);
+        let value = if available == 0 {
+            0
+        } else {
+            ((self.bits >> self.position) as u32) & ((1 << available) - 1)
+        };
+        self.position += count;
+        value
+    }
+}
+
+/// Decode `count` values from the integer sequence encoding as bits and trits or quints.
+fn decode_ise(reader: &mut BitReader, count: usize, quant: Quant) -> Vec<(u32, u32)> {
+    let n = quant.bits;
+    let mut values = Vec::with_capacity(count + 4);
+    while values.len() < count {
+        if quant.trits {
+            let m0 = reader.read(n);
+            let t0 = reader.read(2);
+            let m1 = reader.read(n);
+            let t1 = reader.read(2);
+            let m2 = reader.read(n);
+            let t2 = reader.read(1);
+            let m3 = reader.read(n);
+            let t3 = reader.read(2);
+            let m4 = reader.read(n);
+            let t4 = reader.read(1);
+            let t = decode_trits(t0 | (t1 << 2) | (t2 << 4) | (t3 << 5) | (t4 << 7));
+            values.extend([m0, m1, m2, m3, m4].iter().copied().zip(t.iter().copied()));
+        } else if quant.quints {
+            let m0 = reader.read(n);
+            let q0 = reader.read(3);
+            let m1 = reader.read(n);
+            let q1 = reader.read(2);
+            let m2 = reader.read(n);
+            let q2 = reader.read(2);
+            let q = decode_quints(q0 | (q1 << 3) | (q2 << 5));
+            values.extend([m0, m1, m2].iter().copied().zip(q.iter().copied()));
+        } else {
+            values.push((reader.read(n), 0));
+        }
+    }
+    values.truncate(count);
+    values
+}
+
+fn decode_trits(t: u32) -> [u32; 5] {
+    let bit = |v: u32, i: u32| (v >> i) & 1;
+
+    let (c, t3, t4) = if (t >> 2) & 7 == 7 {
+        ((((t >> 5) & 7) << 2) | (t & 3), 2, 2)
+    } else if (t >> 5) & 3 == 3 {
+        (t & 0x1F, bit(t, 7), 2)
+    } else {
+        (t & 0x1F, (t >> 5) & 3, bit(t, 7))
+    };
+
+    let (t0, t1, t2) = if c & 3 == 3 {
+        let t0 = (bit(c, 3) << 1) | (bit(c, 2) & !bit(c, 3) & 1);
+        (t0, bit(c, 4), 2)
+    } else if (c >> 2) & 3 == 3 {
+        (c & 3, 2, 2)
+    } else {
+        let t0 = (bit(c, 1) << 1) | (bit(c, 0) & !bit(c, 1) & 1);
+        (t0, (c >> 2) & 3, bit(c, 4))
+    };
+
+    [t0, t1, t2, t3, t4]
+}
+
+fn decode_quints(q: u32) -> [u32; 3] {
+    let bit = |v: u32, i: u32| (v >> i) & 1;
+
+    if (q >> 1) & 3 == 3 && (q >> 5) & 3 == 0 {
+        let not_q0 = !q & 1;
+        let q2 = (bit(q, 0) << 2) | ((bit(q, 4) & not_q0) << 1) | (bit(q, 3) & not_q0);
+        return [4, 4, q2];
+    }
+
+    let (c, q2) = if (q >> 1) & 3 == 3 {
+        let c = (((q >> 3) & 3) << 3) | ((!(q >> 5) & 3) << 1) | (q & 1);
+        (c, 4)
+    } else {
+        (q & 0x1F, (q >> 5) & 3)
+    };
+
+    if c & 7 == 5 {
+        [(c >> 3) & 3, 4, q2]
+    } else {
+        [c & 7, (c >> 3) & 3, q2]
+    }
+}
+
+/// Bilinearly interpolate the weight grid to the weight for each texel in the block.
+fn infill_weights(grid: &[u32], mode: &BlockMode, block_width: u32, block_height: u32) -> Vec<u32> {
+    let ds = (1024 + block_width / 2) / (block_width - 1);
+    let dt = (1024 + block_height / 2) / (block_height - 1);
+    let gw = mode.grid_width;
+    let gh = mode.grid_height;
+    let p = |i: u32| grid.get(i as usize).copied().unwrap_or_default();
+
+    (0..block_height)
+        .flat_map(|t| (0..block_width).map(move |s| (s, t)))
+        .map(|(s, t)| {
+            let gs = (ds * s * (gw - 1) + 32) >> 6;
+            let gt = (dt * t * (gh - 1) + 32) >> 6;
+            let (js, fs) = (gs >> 4, gs & 0xF);
+            let (jt, ft) = (gt >> 4, gt & 0xF);
+
+            let v0 = js + jt * gw;
+            let w11 = (fs * ft + 8) >> 4;
+            let w10 = ft - w11;
+            let w01 = fs - w11;
+            let w00 = 16 + w11 - fs - ft;
+
+            (p(v0) * w00 + p(v0 + 1) * w01 + p(v0 + gw) * w10 + p(v0 + gw + 1) * w11 + 8) >> 4
+        })
+        .collect()
+}
+
+/// Select the partition for a texel using the hash function from the specification.
+fn select_partition(seed: u32, x: u32, y: u32, partition_count: u32, small_block: bool) -> usize {
+    let (x, y) = if small_block {
+        (x << 1, y << 1)
+    } else {
+        (x, y)
+    };
+
+    let seed = seed.wrapping_add((partition_count - 1) * 1024);
+    let rnum = hash52(seed);
+    let mut seeds = [
+        rnum & 0xF,
+        (rnum >> 4) & 0xF,
+        (rnum >> 8) & 0xF,
+        (rnum >> 12) & 0xF,
+        (rnum >> 16) & 0xF,
+        (rnum >> 20) & 0xF,
+        (rnum >> 24) & 0xF,
+        (rnum >> 28) & 0xF,
+    ];
+    for s in &mut seeds {
+        *s *= *s;
+    }
+
+    let (sh1, sh2) = if seed & 1 == 1 {
+        (
+            if seed & 2 != 0 { 4 } else { 5 },
+            if partition_count == 3 { 6 } else { 5 },
+        )
+    } else {
+        (
+            if partition_count == 3 { 6 } else { 5 },
+            if seed & 2 != 0 { 4 } else { 5 },
+        )
+    };
+    for (i, s) in seeds.iter_mut().enumerate() {
+        *s >>= if i % 2 == 0 { sh1 } else { sh2 };
+    }
+
+    // The seeds for the z coordinate are omitted since blocks are 2D.
+    let [s1, s2, s3, s4, s5, s6, s7, s8] = seeds;
+    let a = (s1 * x + s2 * y).wrapping_add(rnum >> 14) & 0x3F;
+    let b = (s3 * x + s4 * y).wrapping_add(rnum >> 10) & 0x3F;
+    let c = if partition_count >= 3 {
+        (s5 * x + s6 * y).wrapping_add(rnum >> 6) & 0x3F
+    } else {
+        0
+    };
+    let d = if partition_count >= 4 {
+        (s7 * x + s8 * y).wrapping_add(rnum >> 2) & 0x3F
+    } else {
+        0
+    };
+
+    if a >= b && a >= c && a >= d {
+        0
+    } else if b >= c && b >= d {
+        1
+    } else if c >= d {
+        2
+    } else {
+        3
+    }
+}
+
+fn hash52(p: u32) -> u32 {
+    let mut p = p;
+    p ^= p >> 15;
+    p = p.wrapping_sub(p << 17);
+    p = p.wrapping_add(p << 7);
+    p = p.wrapping_add(p << 4);
+    p ^= p >> 5;
+    p = p.wrapping_add(p << 16);
+    p ^= p >> 7;
+    p ^= p >> 3;
+    p ^= p << 6;
+    p ^= p >> 17;
+    p
+}
+
+fn endpoint_value_count(cem: u32) -> usize {
+    ((cem >> 2) as usize + 1) * 2
+}
+
+/// The endpoint colors for a partition expanded to 16 bits.
+struct Endpoints {
+    low: [u32; 4],
+    high: [u32; 4],
+    /// RGB values are in the logarithmic HDR encoding.
+    hdr_rgb: bool,
+    /// The alpha value is in the logarithmic HDR encoding.
+    hdr_alpha: bool,
+}
+
+impl Endpoints {
+    fn decode(cem: u32, v: &[u32], srgb: bool) -> Self {
+        let v: Vec<i32> = v.iter().map(|v| *v as i32).collect();
+        match cem {
+            2 | 3 | 7 | 11 => {
+                let (low, high) = match cem {
+                    2 => hdr_luminance_large_range(v[0], v[1]),
+                    3 => hdr_luminance_small_range(v[0], v[1]),
+                    7 => hdr_rgb_scale(v[0], v[1], v[2], v[3]),
+                    _ => hdr_rgb(&v),
+                };
+                Self::hdr(low, high, 0x7800, 0x7800, true)
+            }
+            14 => {
+                let (low, high) = hdr_rgb(&v);
+                Self::hdr(low, high, v[6] * 257, v[7] * 257, false)
+            }
+            15 => {
+                let (low, high) = hdr_rgb(&v);
+                let (a0, a1) = hdr_alpha(v[6], v[7]);
+                Self::hdr(low, high, a0 << 4, a1 << 4, true)
+            }
+            _ => {
+                let (low, high) = ldr_endpoints(cem, &v);
+                let expand = |c: i32| {
+                    let c = c.clamp(0, 255) as u32;
+                    if srgb {
+                        (c << 8) | 0x80
+                    } else {
+                        c * 257
+                    }
+                };
+                Self {
+                    low: low.map(expand),
+                    high: high.map(expand),
+                    hdr_rgb: false,
+                    hdr_alpha: false,
+                }
+            }
+        }
+    }
+
+    fn hdr(low: [i32; 3], high: [i32; 3], a0: i32, a1: i32, hdr_alpha: bool) -> Self {
+        let expand = |c: [i32; 3], a: i32| {
+            let [r, g, b] = c.map(|c| (c.clamp(0, 0xFFF) << 4) as u32);
+            [r, g, b, a as u32]
+        };
+        Self {
+            low: expand(low, a0),
+            high: expand(high, a1),
+            hdr_rgb: true,
+            hdr_alpha,
+        }
+    }
+
+    fn is_hdr(&self, channel: usize) -> bool {
+        if channel == 3 {
+            self.hdr_alpha
+        } else {
+            self.hdr_rgb
+        }
+    }
+}
+
+fn ldr_endpoints(cem: u32, v: &[i32]) -> ([i32; 4], [i32; 4]) {
+    match cem {
+        0 => ([v[0], v[0], v[0], 255], [v[1], v[1], v[1], 255]),
+        1 => {
+            let l0 = (v[0] >> 2) | (v[1] & 0xC0);
+            let l1 = (l0 + (v[1] & 0x3F)).min(255);
+            ([l0, l0, l0, 255], [l1, l1, l1, 255])
+        }
+        4 => ([v[0], v[0], v[0], v[2]], [v[1], v[1], v[1], v[3]]),
+        5 => {
+            let (b0, o0) = bit_transfer_signed(v[1], v[0]);
+            let (b2, o2) = bit_transfer_signed(v[3], v[2]);
+            let l1 = b0 + o0;
+            ([b0, b0, b0, b2], [l1, l1, l1, b2 + o2])
+        }
+        6 => (
+            [
+                (v[0] * v[3]) >> 8,
+                (v[1] * v[3]) >> 8,
+                (v[2] * v[3]) >> 8,
+                255,
+            ],
+            [v[0], v[1], v[2], 255],
+        ),
+        8 | 12 => {
+            let (a0, a1) = if cem == 12 { (v[6], v[7]) } else { (255, 255) };
+            if v[1] + v[3] + v[5] >= v[0] + v[2] + v[4] {
+                ([v[0], v[2], v[4], a0], [v[1], v[3], v[5], a1])
+            } else {
+                (
+                    blue_contract(v[1], v[3], v[5], a1),
+                    blue_contract(v[0], v[2], v[4], a0),
+                )
+            }
+        }
+        9 | 13 => {
+            let (r, dr) = bit_transfer_signed(v[1], v[0]);
+            let (g, dg) = bit_transfer_signed(v[3], v[2]);
+            let (b, db) = bit_transfer_signed(v[5], v[4]);
+            let (a, da) = if cem == 13 {
+                bit_transfer_signed(v[7], v[6])
+            } else {
+                (255, 0)
+            };
+            if dr + dg + db >= 0 {
+                ([r, g, b, a], [r + dr, g + dg, b + db, a + da])
+            } else {
+                (
+                    blue_contract(r + dr, g + dg, b + db, a + da),
+                    blue_contract(r, g, b, a),
+                )
+            }
+        }
+        // 10
+        _ => (
+            [
+                (v[0] * v[3]) >> 8,
+                (v[1] * v[3]) >> 8,
+                (v[2] * v[3]) >> 8,
+                v[4],
+            ],
+            [v[0], v[1], v[2], v[5]],
+        ),
+    }
+}
+
+/// Split an 8-bit base and 8-bit offset value into a base and signed 6-bit offset.
+fn bit_transfer_signed(a: i32, b: i32) -> (i32, i32) {
+    let base = (b >> 1) | (a & 0x80);
+    let offset = (a >> 1) & 0x3F;
+    let offset = if offset & 0x20 != 0 {
+        offset - 0x40
+    } else {
+        offset
+    };
+    (base, offset)
+}
+
+fn blue_contract(r: i32, g: i32, b: i32, a: i32) -> [i32; 4] {
+    [(r + b) >> 1, (g + b) >> 1, b, a]
+}
+
+fn hdr_luminance_large_range(v0: i32, v1: i32) -> ([i32; 3], [i32; 3]) {
+    let (y0, y1) = if v1 >= v0 {
+        (v0 << 4, v1 << 4)
+    } else {
+        ((v1 << 4) + 8, (v0 << 4) - 8)
+    };
+    ([y0; 3], [y1; 3])
+}
+
+fn hdr_luminance_small_range(v0: i32, v1: i32) -> ([i32; 3], [i32; 3]) {
+    let (y0, d) = if v0 & 0x80 != 0 {
+        (((v1 & 0xE0) << 4) | ((v0 & 0x7F) << 2), (v1 & 0x1F) << 2)
+    } else {
+        (((v1 & 0xF0) << 4) | ((v0 & 0x7F) << 1), (v1 & 0x0F) << 1)
+    };
+    let y1 = (y0 + d).min(0xFFF);
+    ([y0; 3], [y1; 3])
+}
+
+fn hdr_rgb_scale(v0: i32, v1: i32, v2: i32, v3: i32) -> ([i32; 3], [i32; 3]) {
+    let mode_value = ((v0 & 0xC0) >> 6) | (((v1 & 0x80) >> 7) << 2) | (((v2 & 0x80) >> 7) << 3);
+    let (major, mode) = if mode_value & 0xC != 0xC {
+        (mode_value >> 2, mode_value & 3)
+    } else if mode_value != 0xF {
+        (mode_value & 3, 4)
+    } else {
+        (0, 5)
+    };
+
+    let mut red = v0 & 0x3F;
+    let mut green = v1 & 0x1F;
+    let mut blue = v2 & 0x1F;
+    let mut scale = v3 & 0x1F;
+
+    let bit0 = (v1 >> 6) & 1;
+    let bit1 = (v1 >> 5) & 1;
+    let bit2 = (v2 >> 6) & 1;
+    let bit3 = (v2 >> 5) & 1;
+    let bit4 = (v3 >> 7) & 1;
+    let bit5 = (v3 >> 6) & 1;
+    let bit6 = (v3 >> 5) & 1;
+
+    let one_hot = 1 << mode;
+    if one_hot & 0x30 != 0 {
+        green |= bit0 << 6;
+    }
+    if one_hot & 0x3A != 0 {
+        green |= bit1 << 5;
+    }
+    if one_hot & 0x30 != 0 {
+        blue |= bit2 << 6;
+    }
+    if one_hot & 0x3A != 0 {
+        blue |= bit3 << 5;
+    }
+
+    if one_hot & 0x3D != 0 {
+        scale |= bit6 << 5;
+    }
+    if one_hot & 0x2D != 0 {
+        scale |= bit5 << 6;
+    }
+    if one_hot & 0x04 != 0 {
+        scale |= bit4 << 7;
+    }
+
+    if one_hot & 0x3B != 0 {
+        red |= bit4 << 6;
+    }
+    if one_hot & 0x04 != 0 {
+        red |= bit3 << 6;
+    }
+    if one_hot & 0x10 != 0 {
+        red |= bit5 << 7;
+    }
+    if one_hot & 0x0F != 0 {
+        red |= bit2 << 7;
+    }
+    if one_hot & 0x05 != 0 {
+        red |= bit1 << 8;
+    }
+    if one_hot & 0x0A != 0 {
+        red |= bit0 << 8;
+    }
+    if one_hot & 0x05 != 0 {
+        red |= bit0 << 9;
+    }
+    if one_hot & 0x02 != 0 {
+        red |= bit6 << 9;
+    }
+    if one_hot & 0x01 != 0 {
+        red |= bit3 << 10;
+    }
+    if one_hot & 0x02 != 0 {
+        red |= bit5 << 10;
+    }
+
+    // Expand to 12 bits.
+    let shift = [1, 1, 2, 3, 4, 5][mode as usize];
+    red <<= shift;
+    green <<= shift;
+    blue <<= shift;
+    scale <<= shift;
+
+    // Green and blue are stored relative to red except for the last mode.
+    if mode != 5 {
+        green = red - green;
+        blue = red - blue;
+    }
+
+    match major {
+        1 => std::mem::swap(&mut red, &mut green),
+        2 => std::mem::swap(&mut red, &mut blue),
+        _ => (),
+    }
+
+    (
+        [red - scale, green - scale, blue - scale],
+        [red, green, blue],
+    )
+}
+
+fn hdr_rgb(v: &[i32]) -> ([i32; 3], [i32; 3]) {
+    let mode = ((v[1] & 0x80) >> 7) | (((v[2] & 0x80) >> 7) << 1) | (((v[3] & 0x80) >> 7) << 2);
+    let major = ((v[4] & 0x80) >> 7) | (((v[5] & 0x80) >> 7) << 1);
+
+    if major == 3 {
+        return (
+            [v[0] << 4, v[2] << 4, (v[4] & 0x7F) << 5],
+            [v[1] << 4, v[3] << 4, (v[5] & 0x7F) << 5],
+        );
+    }
+
+    let mut a = v[0] | ((v[1] & 0x40) << 2);
+    let mut b0 = v[2] & 0x3F;
+    let mut b1 = v[3] & 0x3F;
+    let mut c = v[1] & 0x3F;
+    let mut d0 = v[4] & 0x7F;
+    let mut d1 = v[5] & 0x7F;
+
+    let d_bits = [7, 6, 7, 6, 5, 6, 5, 6][mode as usize];
+
+    let bit0 = (v[2] >> 6) & 1;
+    let bit1 = (v[3] >> 6) & 1;
+    let bit2 = (v[4] >> 6) & 1;
+    let bit3 = (v[5] >> 6) & 1;
+    let bit4 = (v[4] >> 5) & 1;
+    let bit5 = (v[5] >> 5) & 1;
+
+    let one_hot = 1 << mode;
+    if one_hot & 0xA4 != 0 {
+        a |= bit0 << 9;
+    }
+    if one_hot & 0x8 != 0 {
+        a |= bit2 << 9;
+    }
+    if one_hot & 0x50 != 0 {
+        a |= bit4 << 9;
+    }
+    if one_hot & 0x50 != 0 {
+        a |= bit5 << 10;
+    }
+    if one_hot & 0xA0 != 0 {
+        a |= bit1 << 10;
+    }
+    if one_hot & 0xC0 != 0 {
+        a |= bit2 << 11;
+    }
+
+    if one_hot & 0x4 != 0 {
+        c |= bit1 << 6;
+    }
+    if one_hot & 0xE8 != 0 {
+        c |= bit3 << 6;
+    }
+    if one_hot & 0x20 != 0 {
+        c |= bit2 << 7;
+    }
+
+    if one_hot & 0x5B != 0 {
+        b0 |= bit0 << 6;
+        b1 |= bit1 << 6;
+    }
+    if one_hot & 0x12 != 0 {
+        b0 |= bit2 << 7;
+        b1 |= bit3 << 7;
+    }
+
+    if one_hot & 0xAF != 0 {
+        d0 |= bit4 << 5;
+        d1 |= bit5 << 5;
+    }
+    if one_hot & 0x5 != 0 {
+        d0 |= bit2 << 6;
+        d1 |= bit3 << 6;
+    }
+
+    // Sign extend the differences.
+    let shift = 32 - d_bits;
+    d0 = (d0 << shift) >> shift;
+    d1 = (d1 << shift) >> shift;
+
+    // Expand to 12 bits.
+    let shift = (mode >> 1) ^ 3;
+    a <<= shift;
+    b0 <<= shift;
+    b1 <<= shift;
+    c <<= shift;
+    d0 <<= shift;
+    d1 <<= shift;
+
+    let mut low = [a - c, a - b0 - c - d0, a - b1 - c - d1];
+    let mut high = [a, a - b0, a - b1];
+    match major {
+        1 => {
+            low.swap(0, 1);
+            high.swap(0, 1);
+        }
+        2 => {
+            low.swap(0, 2);
+            high.swap(0, 2);
+        }
+        _ => (),
+    }
+    (low, high)
+}
+
+fn hdr_alpha(v6: i32, v7: i32) -> (i32, i32) {
+    let selector = ((v6 >> 7) & 1) | ((v7 >> 6) & 2);
+    let mut v6 = v6 & 0x7F;
+    let mut v7 = v7 & 0x7F;
+    if selector == 3 {
+        (v6 << 5, v7 << 5)
+    } else {
+        v6 |= (v7 << (selector + 1)) & 0x780;
+        v7 &= 0x3F >> selector;
+        v7 ^= 32 >> selector;
+        v7 -= 32 >> selector;
+        v6 <<= 4 - selector;
+        v7 <<= 4 - selector;
+        v7 += v6;
+        (v6, v7.clamp(0, 0xFFF))
+    }
+}
+
+/// Convert an interpolated value in the logarithmic HDR encoding to half float bits.
+fn lns_to_f16(value: u32) -> u16 {
+    let e = (value >> 11) & 0x1F;
+    let m = value & 0x7FF;
+    let mt = if m < 512 {
+        3 * m
+    } else if m < 1536 {
+        4 * m - 512
+    } else {
+        5 * m - 2048
+    };
+    ((e << 10) | (mt >> 3)).min(0x7BFF) as u16
+}
+
+fn unorm16_to_f16(value: u32) -> u16 {
+    half::f16::from_f32(value as f32 / 65535.0).to_bits()
+}
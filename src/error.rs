@@ -0,0 +1,153 @@
+use std::{fmt, path::PathBuf};
+
+use binrw::error::BacktraceFrame;
+use thiserror::Error;
+
+use crate::surface::{CreateBntxError, CreateDdsError, CreateSurfaceError};
+
+/// Errors for reading, writing, and converting files.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("error accessing {path:?}")]
+    File {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("error reading {context}")]
+    Read {
+        context: ErrorContext,
+        #[source]
+        source: binrw::Error,
+    },
+
+    #[error("error writing {context}")]
+    Write {
+        context: ErrorContext,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("error creating surface for {context}")]
+    Surface {
+        context: ErrorContext,
+        #[source]
+        source: CreateSurfaceError,
+    },
+
+    #[error("error creating DDS for {context}")]
+    Dds {
+        context: ErrorContext,
+        #[source]
+        source: CreateDdsError,
+    },
+
+    #[error("error creating {context}")]
+    Create {
+        context: ErrorContext,
+        #[source]
+        source: CreateBntxError,
+    },
+
+    #[error("file does not contain any textures")]
+    NoTextures,
+}
+
+/// The location in the file or texture associated with an [Error].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// The magic of the innermost section like `b"BRTI"`.
+    pub magic: Option<[u8; 4]>,
+    /// The absolute offset in the file.
+    pub offset: Option<u64>,
+    pub texture_index: Option<usize>,
+    pub texture_name: Option<String>,
+}
+
+impl ErrorContext {
+    /// The context for the texture at `index`.
+    pub fn texture(index: usize, name: &str) -> Self {
+        Self {
+            magic: Some(*b"BRTI"),
+            offset: None,
+            texture_index: Some(index),
+            texture_name: Some(name.to_string()),
+        }
+    }
+
+    /// Find the innermost section and offset for a read `error`.
+    pub(crate) fn from_read_error(error: &binrw::Error) -> Self {
+        let mut context = Self {
+            offset: error_position(error),
+            ..Default::default()
+        };
+        if let binrw::Error::Backtrace(backtrace) = error {
+            // The first frame is the innermost frame.
+            for frame in &backtrace.frames {
+                if let BacktraceFrame::Custom(custom) = frame {
+                    if let Some(section) = custom.downcast_ref::<Section>() {
+                        context.magic = context.magic.or(Some(section.magic));
+                        context.texture_index = context.texture_index.or(section.index);
+                    }
+                }
+            }
+        }
+        // Errors outside other sections are in the file header.
+        context.magic = context.magic.or(Some(*b"BNTX"));
+        context
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.magic {
+            Some(magic) => write!(f, "{}", String::from_utf8_lossy(&magic).trim_end())?,
+            None => write!(f, "file")?,
+        }
+        if let Some(index) = self.texture_index {
+            write!(f, " texture {index}")?;
+        }
+        if let Some(name) = &self.texture_name {
+            write!(f, " {name:?}")?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at offset 0x{offset:x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Error context for the section being parsed.
+#[derive(Debug)]
+pub(crate) struct Section {
+    pub magic: [u8; 4],
+    pub index: Option<usize>,
+}
+
+impl Section {
+    pub fn new(magic: [u8; 4]) -> Self {
+        Self { magic, index: None }
+    }
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "While parsing {}", String::from_utf8_lossy(&self.magic))?;
+        if let Some(index) = self.index {
+            write!(f, " {index}")?;
+        }
+        Ok(())
+    }
+}
+
+fn error_position(error: &binrw::Error) -> Option<u64> {
+    match error.root_cause() {
+        binrw::Error::BadMagic { pos, .. }
+        | binrw::Error::AssertFail { pos, .. }
+        | binrw::Error::Custom { pos, .. }
+        | binrw::Error::NoVariantMatch { pos }
+        | binrw::Error::EnumErrors { pos, .. } => Some(*pos),
+        _ => None,
+    }
+}
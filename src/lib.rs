@@ -1,18 +1,30 @@
 use binrw::{
-    args, binread, binrw, BinRead, BinReaderExt, BinWrite, FilePtr16, FilePtr32, FilePtr64,
+    binread, binrw, error::ContextExt, BinRead, BinReaderExt, BinResult, BinWrite, FilePtr16,
+    FilePtr32, NullString,
 };
+use error::Section;
+use std::collections::BTreeSet;
 use std::convert::TryFrom;
-use std::io::{Seek, Write};
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+use std::num::NonZeroU32;
 use std::path::Path;
+use surface::CreateDictError;
 use tegra_swizzle::surface::BlockDim;
 use xc3_write::{Endian, WriteFull, Xc3Write, Xc3WriteOffsets};
 
 // TODO: Add module level docs for basic usage.
+mod astc;
+mod error;
+mod linear;
 pub mod surface;
+pub mod texture;
+
+pub use error::{Error, ErrorContext};
+pub use texture::{Mip, Texture};
 
 // TODO: Decompile syroot.nintentools.bntx from switch toolbox to figure out how writing works.
 #[derive(Debug, BinRead, Xc3Write, PartialEq, Clone)]
-#[br(magic = b"BNTX")]
+#[br(magic = b"BNTX", import(options: ReadOptions))]
 #[xc3(magic(b"BNTX"))]
 pub struct Bntx {
     // TODO: always 0?
@@ -22,10 +34,11 @@ pub struct Bntx {
 
     pub bom: ByteOrder,
 
-    #[br(is_little = bom == ByteOrder::LittleEndian)]
+    #[br(is_little = bom == ByteOrder::LittleEndian, args(options))]
     pub header: Header,
 
-    #[br(is_little = bom == ByteOrder::LittleEndian)]
+    #[br(is_little = bom == ByteOrder::LittleEndian, args(options))]
+    #[br(err_context(Section::new(*b"NX  ")))]
     pub nx_header: NxHeader,
 }
 
@@ -40,21 +53,30 @@ pub enum ByteOrder {
 
 #[binread]
 #[derive(Debug, Xc3Write, PartialEq, Clone)]
+#[br(import(options: ReadOptions))]
 pub struct Header {
     pub revision: u16,
 
     // TODO: Should shared offsets prevent deriving write offsets?
+    #[br(restore_position)]
     #[xc3(shared_offset)]
     pub file_name: u32,
 
+    /// The file name pointed to by `file_name`.
+    #[br(parse_with = parse_str_chars_ptr32)]
+    #[xc3(save_position, skip)]
+    pub name: String,
+
     pub unk: u16,
 
-    #[br(parse_with = FilePtr16::parse)]
+    #[br(parse_with = FilePtr16::parse, args { inner: (options,) })]
+    #[br(err_context(Section::new(*b"_STR")))]
     #[xc3(offset(u16))]
     pub str_section: StrSection,
 
     // TODO: The last item in the file?
-    #[br(parse_with = FilePtr32::parse)]
+    #[br(parse_with = FilePtr32::parse, args { inner: (options,) })]
+    #[br(err_context(Section::new(*b"_RLT")))]
     #[xc3(offset(u32))]
     pub reloc_table: RelocationTable,
 
@@ -63,48 +85,53 @@ pub struct Header {
     pub file_size: u32,
 }
 
-// TODO: How to recalculate this when saving?
+/// The offsets of all pointers to adjust when loading the file.
+///
+/// This table is generated from the written pointers when saving,
+/// so the values here are only used when reading.
 #[derive(Debug, BinRead, Xc3Write, PartialEq, Clone)]
-#[br(magic(b"_RLT"))]
+#[br(magic(b"_RLT"), import(options: ReadOptions))]
 #[xc3(magic(b"_RLT"))]
 pub struct RelocationTable {
-    #[xc3(shared_offset)]
     pub position: u32,
     pub count: u32,
     pub unk1: u32, // 0
 
     // TODO: main header section and brtd?
-    #[br(count = count)]
+    #[br(parse_with = parse_count, args(count as usize, options.max_relocation_entries, 24))]
     pub sections: Vec<RelocationSection>,
 
     // TODO: Pointers to string pointers?
-    #[br(count = sections.iter().map(|x| x.count).sum::<u32>())]
+    #[br(parse_with = parse_count)]
+    #[br(args(
+        sections.iter().map(|x| x.count as usize).sum::<usize>(),
+        options.max_relocation_entries,
+        8
+    ))]
     pub entries: Vec<RelocationEntry>,
 }
 
 #[derive(Debug, BinRead, Xc3Write, PartialEq, Clone)]
 pub struct RelocationSection {
     pub pointer: u64,
-    #[xc3(shared_offset)]
     pub position: u32,
-    #[xc3(shared_offset)]
     pub size: u32,
     pub entry_start_index: u32,
     pub count: u32,
 }
 
+/// `struct_count` structs starting at `position` that each have
+/// `offset_count` consecutive pointers followed by `padding_count` non pointer values.
 #[derive(Debug, BinRead, Xc3Write, PartialEq, Clone)]
 pub struct RelocationEntry {
-    #[xc3(shared_offset)]
     pub position: u32,
     pub struct_count: u16,
     pub offset_count: u8,
-    #[xc3(shared_offset)]
     pub padding_count: u8,
 }
 
 #[derive(Debug, BinRead, Xc3Write, PartialEq, Clone)]
-#[br(magic(b"_STR"))]
+#[br(magic(b"_STR"), import(options: ReadOptions))]
 #[xc3(magic(b"_STR"))]
 pub struct StrSection {
     #[xc3(shared_offset)]
@@ -116,7 +143,7 @@ pub struct StrSection {
 
     pub empty: BntxStr,
 
-    #[br(count = str_count)]
+    #[br(parse_with = parse_count, args(str_count as usize, options.max_strings, 4))]
     #[xc3(align_after = 8)]
     pub strings: Vec<BntxStr>,
 }
@@ -137,22 +164,23 @@ pub struct BntxStr {
 
 #[binread]
 #[derive(Debug, Xc3Write, Xc3WriteOffsets, PartialEq, Clone)]
-#[br(magic = b"NX  ")]
+#[br(magic = b"NX  ", import(options: ReadOptions))]
 #[xc3(magic(b"NX  "))]
 pub struct NxHeader {
     #[br(temp)]
     count: u32,
 
-    #[br(parse_with = FilePtr64::parse)]
-    #[br(args { inner: args! { count: count as usize } })]
+    #[br(parse_with = parse_brtis, args(count, options))]
     #[xc3(count_offset(u32, u64))]
     pub brtis: Vec<BrtiOffset>,
 
-    #[br(parse_with = FilePtr64::parse)]
+    #[br(parse_with = parse_ptr64, args(options))]
+    #[br(err_context(Section::new(*b"BRTD")))]
     #[xc3(offset(u64))]
     pub brtd: Brtd,
 
-    #[br(parse_with = FilePtr64::parse)]
+    #[br(parse_with = parse_ptr64, args(options))]
+    #[br(err_context(Section::new(*b"_DIC")))]
     #[xc3(offset(u64))]
     pub dict: DictSection,
     // TODO: How to calculate this
@@ -164,40 +192,135 @@ pub struct NxHeader {
 
 #[derive(Debug, BinRead, Xc3Write, Xc3WriteOffsets, PartialEq, Clone)]
 pub struct BrtiOffset {
-    #[br(parse_with = FilePtr64::parse)]
+    #[br(parse_with = parse_ptr64)]
     #[xc3(offset(u64))]
     pub brti: Brti,
 }
 
-#[derive(Debug, BinRead, BinWrite, PartialEq, Clone)]
-#[brw(magic = b"_DIC")]
+/// A radix tree for finding the index of a texture from its name.
+///
+/// Use [DictSection::from_names] to create a new dictionary.
+#[derive(Debug, BinRead, Xc3Write, Xc3WriteOffsets, PartialEq, Clone)]
+#[br(magic = b"_DIC", import(options: ReadOptions))]
+#[xc3(magic(b"_DIC"), align(8))]
 pub struct DictSection {
     pub node_count: u32,
-    // TODO: some sort of root node is always included?
-    #[br(count = node_count + 1)]
+    /// The root node with an empty name followed by a node for each name.
+    #[br(parse_with = parse_count)]
+    #[br(args(
+        (node_count as usize).saturating_add(1),
+        options.max_textures.saturating_add(1),
+        16
+    ))]
     pub nodes: Vec<DictNode>,
 }
 
-#[derive(Debug, BinRead, BinWrite, PartialEq, Clone)]
+#[derive(Debug, BinRead, Xc3Write, Xc3WriteOffsets, PartialEq, Clone)]
 pub struct DictNode {
+    /// The index of the name bit to test or `-1` for the root node.
     pub reference: i32,
+    /// The node index if the bit is `0`.
     pub left_index: u16,
+    /// The node index if the bit is `1`.
     pub right_index: u16,
-    // TODO: How to correctly calculate this offset?
+
+    #[br(restore_position)]
+    #[xc3(shared_offset)]
     pub name_offset: u64,
+
+    /// The name pointed to by `name_offset`.
+    #[br(parse_with = parse_str_ptr64)]
+    #[xc3(save_position, skip)]
+    pub name: String,
 }
 
-// TODO: Are these flags?
-#[derive(Debug, BinRead, BinWrite, Clone, Copy, PartialEq, Eq)]
-#[brw(repr(u32))]
-pub enum SurfaceFormat {
+macro_rules! surface_formats {
+    ($($variant:ident = $value:literal),* $(,)?) => {
+        /// The image format with the [ChannelLayout] in the high byte
+        /// and the [ChannelType] in the low byte.
+        #[derive(Debug, BinRead, BinWrite, Clone, Copy, PartialEq, Eq)]
+        #[br(map = u32::into)]
+        #[bw(map = |f: &Self| u32::from(*f))]
+        pub enum SurfaceFormat {
+            $($variant,)*
+            /// A format value not recognized by this crate.
+            /// The value is preserved when writing.
+            Unknown(u32),
+        }
+
+        impl From<u32> for SurfaceFormat {
+            fn from(value: u32) -> Self {
+                match value {
+                    $($value => Self::$variant,)*
+                    _ => Self::Unknown(value),
+                }
+            }
+        }
+
+        impl From<SurfaceFormat> for u32 {
+            fn from(value: SurfaceFormat) -> Self {
+                match value {
+                    $(SurfaceFormat::$variant => $value,)*
+                    SurfaceFormat::Unknown(value) => value,
+                }
+            }
+        }
+    };
+}
+
+surface_formats! {
+    R4G4Unorm = 0x0101,
     R8Unorm = 0x0201,
-    Unk1 = 0x0a05,
+    R8Snorm = 0x0202,
+    R8Uint = 0x0203,
+    R8Sint = 0x0204,
+    R4G4B4A4Unorm = 0x0301,
+    A4B4G4R4Unorm = 0x0401,
+    R5G5B5A1Unorm = 0x0501,
+    A1B5G5R5Unorm = 0x0601,
+    R5G6B5Unorm = 0x0701,
+    B5G6R5Unorm = 0x0801,
+    R8G8Unorm = 0x0901,
+    R8G8Snorm = 0x0902,
+    R8G8Uint = 0x0903,
+    R8G8Sint = 0x0904,
+    R16Unorm = 0x0a01,
+    R16Snorm = 0x0a02,
+    R16Uint = 0x0a03,
+    R16Sint = 0x0a04,
+    R16Float = 0x0a05,
     R8G8B8A8Unorm = 0x0b01,
+    R8G8B8A8Snorm = 0x0b02,
+    R8G8B8A8Uint = 0x0b03,
+    R8G8B8A8Sint = 0x0b04,
     R8G8B8A8Srgb = 0x0b06,
     B8G8R8A8Unorm = 0x0c01,
     B8G8R8A8Srgb = 0x0c06,
+    R10G10B10A2Unorm = 0x0e01,
+    R10G10B10A2Uint = 0x0e03,
     R11G11B10 = 0x0f05,
+    R16G16Unorm = 0x1201,
+    R16G16Snorm = 0x1202,
+    R16G16Uint = 0x1203,
+    R16G16Sint = 0x1204,
+    R16G16Float = 0x1205,
+    R32Uint = 0x1403,
+    R32Sint = 0x1404,
+    R32Float = 0x1405,
+    R16G16B16A16Unorm = 0x1501,
+    R16G16B16A16Snorm = 0x1502,
+    R16G16B16A16Uint = 0x1503,
+    R16G16B16A16Sint = 0x1504,
+    R16G16B16A16Float = 0x1505,
+    R32G32Uint = 0x1703,
+    R32G32Sint = 0x1704,
+    R32G32Float = 0x1705,
+    R32G32B32Uint = 0x1803,
+    R32G32B32Sint = 0x1804,
+    R32G32B32Float = 0x1805,
+    R32G32B32A32Uint = 0x1903,
+    R32G32B32A32Sint = 0x1904,
+    R32G32B32A32Float = 0x1905,
     BC1Unorm = 0x1a01,
     BC1Srgb = 0x1a06,
     BC2Unorm = 0x1b01,
@@ -212,7 +335,122 @@ pub enum SurfaceFormat {
     BC6Ufloat = 0x1f0a,
     BC7Unorm = 0x2001,
     BC7Srgb = 0x2006,
-    // TODO: Fill in other known formats.
+    Astc4x4Unorm = 0x2d01,
+    Astc4x4Srgb = 0x2d06,
+    Astc5x4Unorm = 0x2e01,
+    Astc5x4Srgb = 0x2e06,
+    Astc5x5Unorm = 0x2f01,
+    Astc5x5Srgb = 0x2f06,
+    Astc6x5Unorm = 0x3001,
+    Astc6x5Srgb = 0x3006,
+    Astc6x6Unorm = 0x3101,
+    Astc6x6Srgb = 0x3106,
+    Astc8x5Unorm = 0x3201,
+    Astc8x5Srgb = 0x3206,
+    Astc8x6Unorm = 0x3301,
+    Astc8x6Srgb = 0x3306,
+    Astc8x8Unorm = 0x3401,
+    Astc8x8Srgb = 0x3406,
+    Astc10x5Unorm = 0x3501,
+    Astc10x5Srgb = 0x3506,
+    Astc10x6Unorm = 0x3601,
+    Astc10x6Srgb = 0x3606,
+    Astc10x8Unorm = 0x3701,
+    Astc10x8Srgb = 0x3706,
+    Astc10x10Unorm = 0x3801,
+    Astc10x10Srgb = 0x3806,
+    Astc12x10Unorm = 0x3901,
+    Astc12x10Srgb = 0x3906,
+    Astc12x12Unorm = 0x3a01,
+    Astc12x12Srgb = 0x3a06,
+    B5G5R5A1Unorm = 0x3b01,
+}
+
+macro_rules! byte_enum {
+    ($(#[$attr:meta])* $name:ident { $($variant:ident = $value:literal),* $(,)? }) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($variant = $value,)*
+        }
+
+        impl TryFrom<u8> for $name {
+            type Error = u8;
+
+            fn try_from(value: u8) -> Result<Self, Self::Error> {
+                match value {
+                    $($value => Ok(Self::$variant),)*
+                    _ => Err(value),
+                }
+            }
+        }
+    };
+}
+
+byte_enum! {
+    /// The channels and bits per channel for a [SurfaceFormat].
+    ChannelLayout {
+        R4G4 = 0x01,
+        R8 = 0x02,
+        R4G4B4A4 = 0x03,
+        A4B4G4R4 = 0x04,
+        R5G5B5A1 = 0x05,
+        A1B5G5R5 = 0x06,
+        R5G6B5 = 0x07,
+        B5G6R5 = 0x08,
+        R8G8 = 0x09,
+        R16 = 0x0a,
+        R8G8B8A8 = 0x0b,
+        B8G8R8A8 = 0x0c,
+        R9G9B9E5 = 0x0d,
+        R10G10B10A2 = 0x0e,
+        R11G11B10 = 0x0f,
+        B10G11R11 = 0x10,
+        R10G11B11 = 0x11,
+        R16G16 = 0x12,
+        R24G8 = 0x13,
+        R32 = 0x14,
+        R16G16B16A16 = 0x15,
+        R32G8X24 = 0x16,
+        R32G32 = 0x17,
+        R32G32B32 = 0x18,
+        R32G32B32A32 = 0x19,
+        BC1 = 0x1a,
+        BC2 = 0x1b,
+        BC3 = 0x1c,
+        BC4 = 0x1d,
+        BC5 = 0x1e,
+        BC6 = 0x1f,
+        BC7 = 0x20,
+        Astc4x4 = 0x2d,
+        Astc5x4 = 0x2e,
+        Astc5x5 = 0x2f,
+        Astc6x5 = 0x30,
+        Astc6x6 = 0x31,
+        Astc8x5 = 0x32,
+        Astc8x6 = 0x33,
+        Astc8x8 = 0x34,
+        Astc10x5 = 0x35,
+        Astc10x6 = 0x36,
+        Astc10x8 = 0x37,
+        Astc10x10 = 0x38,
+        Astc12x10 = 0x39,
+        Astc12x12 = 0x3a,
+        B5G5R5A1 = 0x3b,
+    }
+}
+
+byte_enum! {
+    /// The numeric interpretation of the channels for a [SurfaceFormat].
+    ChannelType {
+        Unorm = 0x01,
+        Snorm = 0x02,
+        Uint = 0x03,
+        Sint = 0x04,
+        Float = 0x05,
+        Srgb = 0x06,
+        Ufloat = 0x0a,
+    }
 }
 
 #[derive(Debug, BinRead, Xc3Write, Xc3WriteOffsets, PartialEq, Clone)]
@@ -225,7 +463,7 @@ pub struct Brti {
     pub size2: u64, // size?
     pub flags: u8,
     pub texture_dimension: TextureDimension,
-    pub tile_mode: u16,
+    pub tile_mode: TileMode,
     pub swizzle: u16,
     pub mipmap_count: u16,
     pub multi_sample_count: u32,
@@ -239,31 +477,36 @@ pub struct Brti {
     pub unk4: [u32; 6],  // TODO: What is this?
     pub image_size: u32, // the total size of all layers and mipmaps with padding
     pub align: u32, // usually 512 to match the expected mipmap alignment for swizzled surfaces.
-    pub comp_sel: u32,
+    pub comp_sel: ChannelSelector,
     pub texture_view_dimension: TextureViewDimension,
 
-    // TODO: This should point into the string section.
-    // TODO: pointer to bntx string for name
+    /// The offset of the name in the [StrSection].
+    #[br(restore_position)]
     #[xc3(shared_offset)]
     pub name_addr: u64,
 
+    /// The name of the texture pointed to by `name_addr`.
+    #[br(parse_with = parse_str_ptr64)]
+    #[xc3(save_position, skip)]
+    pub name: String,
+
     #[xc3(shared_offset)]
     pub parent_addr: u64, // TODO: pointer to nx header
 
     // TODO: This is a pointer to an array of u64 mipmap offsets.
     // TODO: Parse the entire surface in one vec but store the mipmap offsets?
-    #[br(parse_with = FilePtr64::parse, args { inner: (mipmap_count,)})]
+    #[br(parse_with = parse_ptr64, args(mipmap_count))]
     #[xc3(offset(u64))]
     pub mipmaps: Mipmaps,
 
     pub unk5: u64, // always 0?
 
     // TODO: always 0?
-    #[br(parse_with = FilePtr64::parse)]
+    #[br(parse_with = parse_ptr64)]
     #[xc3(offset(u64))]
     pub unk6: [u8; 256],
 
-    #[br(parse_with = FilePtr64::parse)]
+    #[br(parse_with = parse_ptr64)]
     #[xc3(offset(u64))]
     pub unk7: [u8; 256],
 
@@ -279,6 +522,146 @@ pub enum TextureDimension {
     D3 = 3,
 }
 
+/// The memory layout of the image data.
+#[derive(Debug, BinRead, BinWrite, Clone, Copy, PartialEq, Eq, Default)]
+#[br(map = u16::into)]
+#[bw(map = |m: &Self| u16::from(*m))]
+pub enum TileMode {
+    /// Swizzled using the Tegra X1 block linear layout.
+    #[default]
+    BlockLinear,
+    /// Rows of blocks stored in order with each row padded to 32 bytes.
+    Linear,
+    /// A tile mode not recognized by this crate.
+    /// The value is preserved when writing.
+    Unknown(u16),
+}
+
+impl From<u16> for TileMode {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => Self::BlockLinear,
+            1 => Self::Linear,
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+impl From<TileMode> for u16 {
+    fn from(value: TileMode) -> Self {
+        match value {
+            TileMode::BlockLinear => 0,
+            TileMode::Linear => 1,
+            TileMode::Unknown(value) => value,
+        }
+    }
+}
+
+/// The source for each output channel when sampling a texture.
+///
+/// This is stored as a `u32` with one byte per channel starting from the least significant byte.
+#[derive(Debug, BinRead, BinWrite, Clone, Copy, PartialEq, Eq)]
+#[br(map = u32::into)]
+#[bw(map = |s: &Self| u32::from(*s))]
+pub struct ChannelSelector {
+    pub r: ChannelSource,
+    pub g: ChannelSource,
+    pub b: ChannelSource,
+    pub a: ChannelSource,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelSource {
+    Zero,
+    One,
+    R,
+    G,
+    B,
+    A,
+    /// A source not recognized by this crate.
+    /// The value is preserved when writing.
+    Unknown(u8),
+}
+
+impl Default for ChannelSelector {
+    /// The identity mapping RGBA.
+    fn default() -> Self {
+        Self {
+            r: ChannelSource::R,
+            g: ChannelSource::G,
+            b: ChannelSource::B,
+            a: ChannelSource::A,
+        }
+    }
+}
+
+impl From<u8> for ChannelSource {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Self::Zero,
+            1 => Self::One,
+            2 => Self::R,
+            3 => Self::G,
+            4 => Self::B,
+            5 => Self::A,
+            _ => Self::Unknown(value),
+        }
+    }
+}
+
+impl From<ChannelSource> for u8 {
+    fn from(value: ChannelSource) -> Self {
+        match value {
+            ChannelSource::Zero => 0,
+            ChannelSource::One => 1,
+            ChannelSource::R => 2,
+            ChannelSource::G => 3,
+            ChannelSource::B => 4,
+            ChannelSource::A => 5,
+            ChannelSource::Unknown(value) => value,
+        }
+    }
+}
+
+impl From<u32> for ChannelSelector {
+    fn from(value: u32) -> Self {
+        let [r, g, b, a] = value.to_le_bytes().map(ChannelSource::from);
+        Self { r, g, b, a }
+    }
+}
+
+impl From<ChannelSelector> for u32 {
+    fn from(value: ChannelSelector) -> Self {
+        u32::from_le_bytes([value.r, value.g, value.b, value.a].map(u8::from))
+    }
+}
+
+impl ChannelSelector {
+    /// Returns `true` if all channels use a known [ChannelSource].
+    pub fn is_known(&self) -> bool {
+        [self.r, self.g, self.b, self.a]
+            .iter()
+            .all(|s| !matches!(s, ChannelSource::Unknown(_)))
+    }
+
+    /// Select the channels for a single RGBA8 pixel.
+    /// Unknown sources select `0`.
+    pub fn apply(&self, pixel: [u8; 4]) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a].map(|source| match source {
+            ChannelSource::Zero => 0,
+            ChannelSource::One => 255,
+            ChannelSource::R => pixel[0],
+            ChannelSource::G => pixel[1],
+            ChannelSource::B => pixel[2],
+            ChannelSource::A => pixel[3],
+            ChannelSource::Unknown(_) => 0,
+        })
+    }
+}
+
+/// The dimension used when sampling the texture.
+///
+/// Cube maps store 6 layers for each cube in the order +X, -X, +Y, -Y, +Z, -Z like DDS.
 #[derive(Debug, BinRead, BinWrite, Clone, Copy, PartialEq, Eq)]
 #[brw(repr(u32))]
 pub enum TextureViewDimension {
@@ -286,84 +669,394 @@ pub enum TextureViewDimension {
     D2 = 1,
     D3 = 2,
     Cube = 3,
-    // TODO: Fill in other known variants
+    D1Array = 4,
+    D2Array = 5,
+    D2Multisample = 6,
+    D2MultisampleArray = 7,
+    CubeArray = 8,
+}
+
+impl TextureViewDimension {
+    /// Returns `true` for [TextureViewDimension::Cube] and [TextureViewDimension::CubeArray].
+    pub fn is_cube(&self) -> bool {
+        matches!(self, Self::Cube | Self::CubeArray)
+    }
+
+    /// Returns `true` for [TextureViewDimension::D1] and [TextureViewDimension::D1Array].
+    pub fn is_1d(&self) -> bool {
+        matches!(self, Self::D1 | Self::D1Array)
+    }
+
+    /// The dimension of the image data for this view dimension.
+    pub fn texture_dimension(&self) -> TextureDimension {
+        match self {
+            Self::D1 | Self::D1Array => TextureDimension::D1,
+            Self::D3 => TextureDimension::D3,
+            _ => TextureDimension::D2,
+        }
+    }
 }
 
 #[binrw]
 #[brw(magic = b"BRTD")]
+#[br(import(options: ReadOptions))]
 #[derive(Debug, PartialEq, Clone)]
 pub struct Brtd {
+    pub unk: u32, // always 0?
+
     // Size of the image data + BRTD header.
-    #[brw(pad_before = 4)]
-    #[br(temp)]
+    #[br(temp, assert(brtd_size >= 16, "invalid BRTD size {}", brtd_size))]
     #[bw(calc = image_data.len() as u64 + 16)]
     brtd_size: u64,
 
-    #[br(count = brtd_size - 16)]
+    /// The absolute offset of the start of `image_data` in the file.
+    /// This is used to find the data for each [Mipmaps] offset
+    /// and to keep the original layout when writing.
+    #[br(parse_with = stream_position)]
+    #[bw(ignore)]
+    pub data_offset: u64,
+
+    #[br(parse_with = parse_count)]
+    #[br(args(
+        usize::try_from(brtd_size - 16).unwrap_or(usize::MAX),
+        options.max_image_bytes,
+        1
+    ))]
     pub image_data: Vec<u8>,
 }
 
 #[derive(Debug, BinRead, BinWrite, PartialEq, Clone)]
 #[br(import(mipmap_count: u16))]
 pub struct Mipmaps {
-    #[br(count = mipmap_count)]
+    #[br(parse_with = parse_count, args(mipmap_count as usize, u16::MAX as usize, 8))]
     pub mipmap_offsets: Vec<u64>,
 }
 
+/// Limits for reading untrusted files.
+///
+/// Counts are also checked against the length of the stream before allocating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// The maximum number of textures and dictionary names.
+    pub max_textures: usize,
+    /// The maximum number of strings in the string section.
+    pub max_strings: usize,
+    /// The maximum size in bytes of the image data for all textures.
+    pub max_image_bytes: usize,
+    /// The maximum number of relocation sections and entries.
+    pub max_relocation_entries: usize,
+    /// The maximum total bytes read as a multiple of the stream length.
+    ///
+    /// Textures and names can point to the same data,
+    /// so this limits allocations for files that reuse the same pointers many times.
+    pub max_read_multiplier: u64,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            // Dictionary nodes use u16 indices.
+            max_textures: u16::MAX as usize,
+            max_strings: 1 << 20,
+            max_image_bytes: isize::MAX as usize,
+            max_relocation_entries: 1 << 20,
+            // Names are read once for the dictionary and once for each texture.
+            max_read_multiplier: 4,
+        }
+    }
+}
+
 impl Bntx {
-    pub fn width(&self) -> u32 {
-        self.nx_header.brtis[0].brti.width
+    /// All the textures in the order they appear in the file.
+    pub fn textures(&self) -> impl ExactSizeIterator<Item = Texture<'_>> {
+        let brtd = &self.nx_header.brtd;
+        self.nx_header
+            .brtis
+            .iter()
+            .map(move |b| Texture::new(&b.brti, brtd))
+    }
+
+    /// The texture at `index` or `None` if out of range.
+    pub fn texture(&self, index: usize) -> Option<Texture<'_>> {
+        self.nx_header
+            .brtis
+            .get(index)
+            .map(|b| Texture::new(&b.brti, &self.nx_header.brtd))
     }
 
-    pub fn height(&self) -> u32 {
-        self.nx_header.brtis[0].brti.height
+    /// The index of the texture with the given `name` from the dictionary
+    /// or `None` if not found.
+    pub fn find_texture(&self, name: &str) -> Option<usize> {
+        self.nx_header.dict.find(name)
     }
 
-    pub fn depth(&self) -> u32 {
-        self.nx_header.brtis[0].brti.depth
+    /// The file name stored in the string section.
+    pub fn name(&self) -> &str {
+        &self.header.name
     }
 
-    pub fn layer_count(&self) -> u32 {
-        self.nx_header.brtis[0].brti.layer_count
+    /// The first texture with the given `name` or `None` if not found.
+    pub fn texture_by_name(&self, name: &str) -> Option<Texture<'_>> {
+        self.textures().find(|t| t.name() == name)
     }
 
-    pub fn mipmap_count(&self) -> u32 {
-        self.nx_header.brtis[0].brti.mipmap_count as u32
+    /// The first texture or `None` if the file has no textures.
+    pub fn first_texture(&self) -> Option<Texture<'_>> {
+        self.texture(0)
     }
 
-    pub fn image_format(&self) -> SurfaceFormat {
-        self.nx_header.brtis[0].brti.image_format
+    /// The width of the first texture.
+    pub fn width(&self) -> Option<u32> {
+        self.first_texture().map(|t| t.width())
     }
 
-    /// The deswizzled image data for all layers and mipmaps.
-    pub fn deswizzled_data(&self) -> Result<Vec<u8>, tegra_swizzle::SwizzleError> {
-        let info = &self.nx_header.brtis[0].brti;
+    /// The height of the first texture.
+    pub fn height(&self) -> Option<u32> {
+        self.first_texture().map(|t| t.height())
+    }
 
-        tegra_swizzle::surface::deswizzle_surface(
-            info.width,
-            info.height,
-            info.depth,
-            &self.nx_header.brtd.image_data,
-            info.image_format.block_dim(),
-            None, // TODO: use block height from header?
-            info.image_format.bytes_per_pixel(),
-            info.mipmap_count as u32,
-            info.layer_count,
-        )
+    /// The depth of the first texture.
+    pub fn depth(&self) -> Option<u32> {
+        self.first_texture().map(|t| t.depth())
+    }
+
+    /// The array layer count of the first texture.
+    pub fn layer_count(&self) -> Option<u32> {
+        self.first_texture().map(|t| t.layer_count())
+    }
+
+    /// The mipmap count of the first texture.
+    pub fn mipmap_count(&self) -> Option<u32> {
+        self.first_texture().map(|t| t.mipmap_count())
+    }
+
+    /// The format of the first texture.
+    pub fn image_format(&self) -> Option<SurfaceFormat> {
+        self.first_texture().map(|t| t.image_format())
+    }
+
+    /// The deswizzled image data for all layers and mipmaps of the first texture.
+    pub fn deswizzled_data(&self) -> Result<Vec<u8>, Error> {
+        let texture = self.first_texture().ok_or(Error::NoTextures)?;
+        texture.deswizzled_data().map_err(|source| Error::Surface {
+            context: ErrorContext::texture(0, texture.name()),
+            source,
+        })
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_file_with_options(path, ReadOptions::default())
+    }
+
+    /// Read a file from `path` with the limits in `options`.
+    pub fn from_file_with_options<P: AsRef<Path>>(
+        path: P,
+        options: ReadOptions,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path).map_err(|source| Error::File {
+            path: path.to_owned(),
+            source,
+        })?;
+        Self::read_with_options(&mut std::io::BufReader::new(file), options)
+    }
+
+    /// Read a file using the byte order from [Bntx::bom].
+    /// Offsets in the file are relative to the current position of `reader`.
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self, Error> {
+        Self::read_with_options(reader, ReadOptions::default())
+    }
+
+    /// Read a file with the limits in `options`.
+    /// Offsets in the file are relative to the current position of `reader`.
+    pub fn read_with_options<R: Read + Seek>(
+        reader: &mut R,
+        options: ReadOptions,
+    ) -> Result<Self, Error> {
+        read_bntx(reader, options).map_err(|source| Error::Read {
+            context: ErrorContext::from_read_error(&source),
+            source,
+        })
+    }
+
+    /// Read a file from `bytes` using the byte order from [Bntx::bom].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::read(&mut Cursor::new(bytes))
     }
-    // TODO: from_image_data?
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, binrw::error::Error> {
-        let mut reader = std::io::BufReader::new(std::fs::File::open(path)?);
-        reader.read_le()
+
+    /// Read a file from `bytes` with the limits in `options`.
+    pub fn from_bytes_with_options(bytes: &[u8], options: ReadOptions) -> Result<Self, Error> {
+        Self::read_with_options(&mut Cursor::new(bytes), options)
     }
 
-    pub fn write<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
-        self.write_full(writer, 0, &mut 0, Endian::Little, ())
+    /// Write the file using the byte order from [Bntx::bom].
+    ///
+    /// Names missing from the string section are added,
+    /// and the dictionary is rebuilt if the texture names changed.
+    pub fn write<W: Write + Seek>(&self, writer: &mut W) -> Result<(), Error> {
+        let renamed = self.with_updated_names()?;
+        let bntx = renamed.as_ref().unwrap_or(self);
+        bntx.write_full(writer, 0, &mut 0, self.bom.into(), ())
+            .map_err(|source| Error::Write {
+                context: ErrorContext {
+                    offset: writer.stream_position().ok(),
+                    ..Default::default()
+                },
+                source,
+            })
     }
 
-    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
-        let mut writer = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
-        self.write(&mut writer)
+    /// Write the file to a new buffer using the byte order from [Bntx::bom].
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut writer = Cursor::new(Vec::new());
+        self.write(&mut writer)?;
+        Ok(writer.into_inner())
+    }
+
+    // Renamed textures need a string and an updated dictionary.
+    // Return `None` if nothing changed to avoid copying the image data.
+    fn with_updated_names(&self) -> Result<Option<Self>, Error> {
+        let texture_names: Vec<_> = self
+            .nx_header
+            .brtis
+            .iter()
+            .map(|b| b.brti.name.as_str())
+            .collect();
+
+        let strings = &self.header.str_section.strings;
+        let mut missing_strings = Vec::new();
+        for name in std::iter::once(self.name()).chain(texture_names.iter().copied()) {
+            if !name.is_empty()
+                && !strings.iter().any(|s| s.chars == name)
+                && !missing_strings.contains(&name)
+            {
+                missing_strings.push(name);
+            }
+        }
+
+        // The node for each texture follows the root node.
+        let dict = &self.nx_header.dict;
+        let dict_matches = dict.node_count as usize == texture_names.len()
+            && dict.nodes.len() == texture_names.len() + 1
+            && dict.nodes[1..]
+                .iter()
+                .zip(&texture_names)
+                .all(|(node, name)| node.name == *name);
+
+        if missing_strings.is_empty() && dict_matches {
+            return Ok(None);
+        }
+
+        let mut bntx = self.clone();
+        let str_section = &mut bntx.header.str_section;
+        str_section
+            .strings
+            .extend(missing_strings.into_iter().map(|name| BntxStr {
+                chars: name.to_string(),
+            }));
+        str_section.str_count = str_section.strings.len() as u32;
+        if !dict_matches {
+            bntx.nx_header.dict = surface::create_dict(&texture_names)?;
+        }
+        Ok(Some(bntx))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let file = std::fs::File::create(path).map_err(|source| Error::File {
+            path: path.to_owned(),
+            source,
+        })?;
+        let mut writer = std::io::BufWriter::new(file);
+        self.write(&mut writer)?;
+        writer.flush().map_err(|source| Error::File {
+            path: path.to_owned(),
+            source,
+        })
+    }
+}
+
+// Fields before the byte order mark also depend on the byte order.
+fn read_bntx<R: Read + Seek>(reader: &mut R, options: ReadOptions) -> BinResult<Bntx> {
+    let start = reader.stream_position()?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(start + 12))?;
+    let bom: ByteOrder = reader.read_le()?;
+    reader.seek(SeekFrom::Start(start))?;
+
+    let limit = end
+        .saturating_sub(start)
+        .saturating_mul(options.max_read_multiplier);
+    let mut reader = LimitedReader {
+        reader,
+        start,
+        limit,
+        remaining: limit,
+    };
+    reader.read_type_args(bom.into(), (options,))
+}
+
+// Count bytes read across all pointers since pointed to data can be shared.
+// Positions are relative to `start` since offsets in the file are relative to the header.
+struct LimitedReader<R> {
+    reader: R,
+    start: u64,
+    limit: u64,
+    remaining: u64,
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.remaining == 0 && !buf.is_empty() {
+            return Err(std::io::Error::other(format!(
+                "total bytes read exceeds the limit of {}",
+                self.limit
+            )));
+        }
+        let max_count = buf
+            .len()
+            .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let count = self.reader.read(&mut buf[..max_count])?;
+        self.remaining -= count as u64;
+        Ok(count)
+    }
+}
+
+impl<R: Seek> Seek for LimitedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => {
+                SeekFrom::Start(self.start.checked_add(offset).ok_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidInput, "offset overflows")
+                })?)
+            }
+            pos => pos,
+        };
+        let position = self.reader.seek(pos)?;
+        position.checked_sub(self.start).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seek before the start of the file",
+            )
+        })
+    }
+}
+
+impl From<ByteOrder> for binrw::Endian {
+    fn from(value: ByteOrder) -> Self {
+        match value {
+            ByteOrder::LittleEndian => binrw::Endian::Little,
+            ByteOrder::BigEndian => binrw::Endian::Big,
+        }
+    }
+}
+
+impl From<ByteOrder> for Endian {
+    fn from(value: ByteOrder) -> Self {
+        match value {
+            ByteOrder::LittleEndian => Endian::Little,
+            ByteOrder::BigEndian => Endian::Big,
+        }
     }
 }
 
@@ -378,11 +1071,22 @@ impl<'a> Xc3WriteOffsets for BntxOffsets<'a> {
         endian: Endian,
         _args: Self::Args,
     ) -> std::io::Result<()> {
+        // Pointers to data before the BRTD section.
+        let mut data_pointers = vec![
+            self.nx_header.brtis.position,
+            self.nx_header.dict.position,
+            // The dict size is an offset to the memory pool.
+            self.nx_header.dict.position + 8,
+        ];
+        // Pointers to data in the BRTD section.
+        let mut brtd_pointers = vec![self.nx_header.brtd.position];
+
         // Match the convention for ordering of data items in bntx files.
         let brtis = self
             .nx_header
             .brtis
             .write(writer, base_offset, data_ptr, endian)?;
+        data_pointers.extend(brtis.0.iter().map(|b| b.brti.position));
 
         // TODO: Add an attribute for storing positions of fields and types?
         let str_section_pos = *data_ptr;
@@ -390,16 +1094,25 @@ impl<'a> Xc3WriteOffsets for BntxOffsets<'a> {
             .header
             .str_section
             .write(writer, base_offset, data_ptr, endian)?;
+        let string_offsets = self.header.str_section.data.string_offsets(str_section_pos);
 
-        // Point to the string chars.
-        self.header
-            .file_name
-            .set_offset(writer, str_section_pos + 26, endian)?;
+        // Point to the string chars after the length.
+        self.header.file_name.set_offset(
+            writer,
+            string_offsets.offset(self.header.name.data)? + 2,
+            endian,
+        )?;
 
-        let dict_pos = data_ptr.next_multiple_of(8);
-        self.nx_header
+        let dict = self
+            .nx_header
             .dict
-            .write_full(writer, base_offset, data_ptr, endian, ())?;
+            .write(writer, base_offset, data_ptr, endian)?;
+
+        for node in &dict.nodes.0 {
+            node.name_offset
+                .set_offset(writer, string_offsets.offset(node.name.data)?, endian)?;
+            data_pointers.push(node.name_offset.position);
+        }
 
         // TODO: why does the str section point past the dict section?
         str_section
@@ -409,19 +1122,16 @@ impl<'a> Xc3WriteOffsets for BntxOffsets<'a> {
             .block_size
             .set_offset(writer, *data_ptr - str_section_pos, endian)?;
 
-        let brtis_pos = *data_ptr;
-        for brti in brtis.0 {
-            let brti_position = *data_ptr;
+        let mut brti_positions = Vec::new();
+        let mut brti_offsets = Vec::new();
+        let mut mipmaps_positions = Vec::new();
+        for brti in &brtis.0 {
+            brti_positions.push(*data_ptr);
             let brti = brti.brti.write(writer, base_offset, data_ptr, endian)?;
 
-            // TODO: How to set this if there is more than 1 BRTI?
-            brti.size.set_offset(writer, 4080 - brti_position, endian)?;
-            brti.size2
-                .set_offset(writer, 4080 - brti_position, endian)?;
-
             // Point to the bntx string.
             brti.name_addr
-                .set_offset(writer, str_section_pos + 24, endian)?;
+                .set_offset(writer, string_offsets.offset(brti.name.data)?, endian)?;
 
             // TODO: nx address?
             brti.parent_addr.set_offset(writer, 32, endian)?;
@@ -430,81 +1140,94 @@ impl<'a> Xc3WriteOffsets for BntxOffsets<'a> {
                 .write_full(writer, base_offset, data_ptr, endian, ())?;
             brti.unk7
                 .write_full(writer, base_offset, data_ptr, endian, ())?;
+
+            // The offsets are updated later once the BRTD position is known.
             brti.mipmaps
                 .write_full(writer, base_offset, data_ptr, endian, ())?;
+            let mipmaps_pos = *data_ptr - brti.mipmaps.data.mipmap_offsets.len() as u64 * 8;
+            mipmaps_positions.push(mipmaps_pos);
+
+            data_pointers.extend([
+                brti.name_addr.position,
+                brti.parent_addr.position,
+                brti.mipmaps.position,
+                brti.unk6.position,
+                brti.unk7.position,
+            ]);
+            // Only the first mipmap offset is relocated to match existing files.
+            if !brti.mipmaps.data.mipmap_offsets.is_empty() {
+                brtd_pointers.push(mipmaps_pos);
+            }
+
+            brti_offsets.push(brti);
         }
         let after_brti_pos = *data_ptr;
 
-        // TODO: Is this fixed padding?
-        *data_ptr = 4080;
+        // Keep the original position of the image data if possible to preserve the layout.
+        // Otherwise align the start of the image data.
+        let old_data_pos = self.nx_header.brtd.data.data_offset;
+        let brtd_pos = if old_data_pos >= after_brti_pos + 16 {
+            old_data_pos - 16
+        } else {
+            (after_brti_pos + 16).next_multiple_of(BRTD_DATA_ALIGNMENT) - 16
+        };
+        *data_ptr = brtd_pos;
         self.nx_header
             .brtd
             .write_full(writer, base_offset, data_ptr, endian, ())?;
 
-        let reloc_table_pos = *data_ptr;
-        let reloc_table = self
-            .header
-            .reloc_table
-            .write(writer, base_offset, data_ptr, endian)?;
-        reloc_table
-            .position
-            .set_offset(writer, reloc_table_pos, endian)?;
-
-        // Data until end of BRTIs
-        reloc_table.sections.0[0]
-            .size
-            .set_offset(writer, after_brti_pos, endian)?;
+        // Each BRTI block extends to the start of the next block.
+        for (i, brti) in brti_offsets.iter().enumerate() {
+            let next_block_pos = brti_positions.get(i + 1).copied().unwrap_or(brtd_pos);
+            let size = next_block_pos - brti_positions[i];
+            brti.size.set_offset(writer, size, endian)?;
+            brti.size2.set_offset(writer, size, endian)?;
+        }
 
-        // BRTD to _RLT
-        reloc_table.sections.0[1]
-            .position
-            .set_offset(writer, 4080, endian)?;
-        reloc_table.sections.0[1].size.set_offset(
-            writer,
-            self.nx_header.brtd.data.image_data.len() as u64 + 16,
-            endian,
-        )?;
+        // Mipmap offsets are absolute, so adjust them for the new data position.
+        let new_data_pos = brtd_pos + 16;
+        for (brti, position) in brti_offsets.iter().zip(&mipmaps_positions) {
+            let mipmaps = Mipmaps {
+                mipmap_offsets: brti
+                    .mipmaps
+                    .data
+                    .mipmap_offsets
+                    .iter()
+                    .map(|o| o.wrapping_sub(old_data_pos).wrapping_add(new_data_pos))
+                    .collect(),
+            };
+            writer.seek(SeekFrom::Start(*position))?;
+            mipmaps.xc3_write(writer, endian)?;
+        }
 
-        // TODO: Set positions automatically without assuming entry order and count?
-        // TODO: There's some sort of relationship where padding count and position increase together.
-        let padding = brtis_pos / 8 + 7;
-
-        // _RLT Section 0
-        reloc_table.entries.0[0]
-            .position
-            .set_offset(writer, 40, endian)?;
-        reloc_table.entries.0[0]
-            .padding_count
-            .set_offset(writer, 45, endian)?;
-
-        reloc_table.entries.0[1]
-            .position
-            .set_offset(writer, 56, endian)?;
-        reloc_table.entries.0[1]
-            .padding_count
-            .set_offset(writer, padding, endian)?;
-
-        // _DIC str offsets
-        reloc_table.entries.0[2]
-            .position
-            .set_offset(writer, dict_pos + 16, endian)?;
-        reloc_table.entries.0[2]
-            .padding_count
-            .set_offset(writer, 1, endian)?;
-
-        // _BRTI str offset
-        reloc_table.entries.0[3]
-            .position
-            .set_offset(writer, brtis_pos + 96, endian)?;
-
-        // _RLT Section 1
-        // BRTD offset
-        reloc_table.entries.0[4]
-            .position
-            .set_offset(writer, 48, endian)?;
-        reloc_table.entries.0[4]
-            .padding_count
-            .set_offset(writer, padding + 70, endian)?;
+        let reloc_table_pos = data_ptr.next_multiple_of(8);
+        let mut reloc_table = RelocationTable::new(
+            reloc_table_pos,
+            &[
+                // Data until end of BRTIs
+                (0, after_brti_pos, data_pointers),
+                // BRTD to _RLT
+                (
+                    brtd_pos,
+                    self.nx_header.brtd.data.image_data.len() as u64 + 16,
+                    brtd_pointers,
+                ),
+            ],
+        );
+        // Files may group the same pointers into entries differently.
+        let original_table = self.header.reloc_table.data;
+        if original_table.has_same_pointers(&reloc_table) {
+            reloc_table = RelocationTable {
+                position: reloc_table.position,
+                ..original_table.clone()
+            };
+        }
+        self.header
+            .reloc_table
+            .set_offset(writer, reloc_table_pos, endian)?;
+        writer.seek(SeekFrom::Start(reloc_table_pos))?;
+        reloc_table.xc3_write(writer, endian)?;
+        *data_ptr = writer.stream_position()?;
 
         // This fills in the file size since we write it last.
         self.header
@@ -514,60 +1237,687 @@ impl<'a> Xc3WriteOffsets for BntxOffsets<'a> {
     }
 }
 
+impl RelocationTable {
+    /// Create a table for the pointers in each section of `(position, size, pointers)`.
+    fn new(position: u64, sections: &[(u64, u64, Vec<u64>)]) -> Self {
+        let mut table_sections = Vec::new();
+        let mut entries = Vec::new();
+        for (section_position, size, pointers) in sections {
+            let section_entries = relocation_entries(pointers);
+            table_sections.push(RelocationSection {
+                pointer: 0,
+                position: *section_position as u32,
+                size: *size as u32,
+                entry_start_index: entries.len() as u32,
+                count: section_entries.len() as u32,
+            });
+            entries.extend(section_entries);
+        }
+
+        Self {
+            position: position as u32,
+            count: table_sections.len() as u32,
+            unk1: 0,
+            sections: table_sections,
+            entries,
+        }
+    }
+
+    // Check if both tables relocate the same pointers for each section.
+    fn has_same_pointers(&self, other: &Self) -> bool {
+        self.sections.len() == other.sections.len()
+            && self.sections.iter().zip(&other.sections).all(|(a, b)| {
+                a.position == b.position
+                    && a.size == b.size
+                    && match (self.section_pointers(a), other.section_pointers(b)) {
+                        (Some(a), Some(b)) => a == b,
+                        _ => false,
+                    }
+            })
+    }
+
+    // The pointer positions for a section or `None` if the entries are invalid.
+    fn section_pointers(&self, section: &RelocationSection) -> Option<BTreeSet<u64>> {
+        let start = section.entry_start_index as usize;
+        let entries = self
+            .entries
+            .get(start..start.checked_add(section.count as usize)?)?;
+
+        // Avoid expanding entries that point to more pointers than could fit in the file.
+        let pointer_count: u64 = entries
+            .iter()
+            .map(|e| e.struct_count as u64 * e.offset_count as u64)
+            .sum();
+        if pointer_count > self.position as u64 / 8 {
+            return None;
+        }
+
+        let mut pointers = BTreeSet::new();
+        for entry in entries {
+            let stride = (entry.offset_count as u64 + entry.padding_count as u64) * 8;
+            for i in 0..entry.struct_count as u64 {
+                let struct_position = entry.position as u64 + i * stride;
+                pointers.extend((0..entry.offset_count as u64).map(|j| struct_position + j * 8));
+            }
+        }
+        Some(pointers)
+    }
+}
+
+fn relocation_entries(pointers: &[u64]) -> Vec<RelocationEntry> {
+    let mut remaining: BTreeSet<u64> = pointers.iter().copied().collect();
+
+    // The number of consecutive pointers starting at position.
+    let run_length = |remaining: &BTreeSet<u64>, position: u64| {
+        (0..u8::MAX as u64)
+            .take_while(|i| remaining.contains(&(position + i * 8)))
+            .count() as u8
+    };
+    let is_run = |remaining: &BTreeSet<u64>, position: u64, offset_count: u8| {
+        !remaining.contains(&position.wrapping_sub(8))
+            && run_length(remaining, position) == offset_count
+    };
+    let remove_run = |remaining: &mut BTreeSet<u64>, position: u64, offset_count: u8| {
+        for i in 0..offset_count as u64 {
+            remaining.remove(&(position + i * 8));
+        }
+    };
+
+    // Combine repeated structs with the same pointer layout into a single entry.
+    let mut entries = Vec::new();
+    while let Some(position) = remaining.first().copied() {
+        let offset_count = run_length(&remaining, position);
+        remove_run(&mut remaining, position, offset_count);
+
+        let mut struct_count = 1;
+        let mut padding_count = 0;
+
+        let end = position + offset_count as u64 * 8;
+        let next = remaining
+            .range(end..)
+            .copied()
+            .find(|p| is_run(&remaining, *p, offset_count));
+        if let Some(next) = next {
+            let padding = (next - end) / 8;
+            if (next - end).is_multiple_of(8) && padding <= u8::MAX as u64 {
+                padding_count = padding as u8;
+
+                let stride = next - position;
+                let mut struct_position = next;
+                while struct_count < u16::MAX && is_run(&remaining, struct_position, offset_count) {
+                    remove_run(&mut remaining, struct_position, offset_count);
+                    struct_count += 1;
+                    struct_position += stride;
+                }
+            }
+        }
+
+        entries.push(RelocationEntry {
+            position: position as u32,
+            struct_count,
+            offset_count,
+            padding_count,
+        });
+    }
+    entries
+}
+
+// The image data starts at a page aligned offset.
+const BRTD_DATA_ALIGNMENT: u64 = 4096;
+
+/// The absolute offsets of the strings in a [StrSection].
+struct StringOffsets<'a> {
+    empty: u64,
+    strings: &'a [BntxStr],
+    offsets: Vec<u64>,
+}
+
+impl StringOffsets<'_> {
+    fn offset(&self, name: &str) -> std::io::Result<u64> {
+        if name.is_empty() {
+            return Ok(self.empty);
+        }
+
+        self.strings
+            .iter()
+            .position(|s| s.chars == name)
+            .map(|i| self.offsets[i])
+            .ok_or_else(|| {
+                std::io::Error::other(format!("name {name:?} not found in the string section"))
+            })
+    }
+}
+
+impl StrSection {
+    fn string_offsets(&self, position: u64) -> StringOffsets<'_> {
+        // Each string is a u16 length followed by the null terminated chars aligned to 2.
+        let string_size = |s: &BntxStr| (s.chars.len() as u64 + 3).next_multiple_of(2);
+
+        let empty = position + 20;
+        let mut offset = empty + string_size(&self.empty);
+        let mut offsets = Vec::new();
+        for s in &self.strings {
+            offsets.push(offset);
+            offset += string_size(s);
+        }
+
+        StringOffsets {
+            empty,
+            strings: &self.strings,
+            offsets,
+        }
+    }
+}
+
+impl DictSection {
+    /// Build the radix tree for finding the index of each name.
+    /// The node for `names[i]` has index `i + 1`.
+    ///
+    /// Returns an error if a name is empty or can't be told apart from another name
+    /// or if there are too many names for the 16-bit node indices.
+    pub fn from_names(names: &[&str]) -> Result<Self, CreateDictError> {
+        // The root node takes index 0.
+        if names.len() > u16::MAX as usize {
+            return Err(CreateDictError::TooManyNames(names.len()));
+        }
+
+        // The root node always has an empty name.
+        let mut nodes = vec![DictNode {
+            reference: -1,
+            left_index: 0,
+            right_index: 0,
+            name_offset: 0,
+            name: String::new(),
+        }];
+
+        for name in names {
+            if name.is_empty() {
+                return Err(CreateDictError::EmptyName);
+            }
+
+            // The new node splits the tree at the first bit that differs from the closest name.
+            let closest = &nodes[search_dict(&nodes, name).unwrap_or(0)].name;
+            let bit = first_different_bit(name, closest).ok_or_else(|| {
+                if closest == name {
+                    CreateDictError::DuplicateName(name.to_string())
+                } else {
+                    // Missing bytes compare as zero, so "\0a" and "a" have the same bits.
+                    CreateDictError::IndistinguishableNames(name.to_string(), closest.clone())
+                }
+            })?;
+
+            let mut parent = 0;
+            let mut child = nodes[0].left_index as usize;
+            while nodes[parent].reference < nodes[child].reference
+                && nodes[child].reference < bit as i32
+            {
+                parent = child;
+                child = nodes[child].child_index(name);
+            }
+
+            let index = nodes.len() as u16;
+            let (left_index, right_index) = if name_bit(name, bit) {
+                (child as u16, index)
+            } else {
+                (index, child as u16)
+            };
+            nodes.push(DictNode {
+                reference: bit as i32,
+                left_index,
+                right_index,
+                name_offset: 0,
+                name: name.to_string(),
+            });
+
+            // The root only uses the left child.
+            if parent == 0 || !name_bit(name, nodes[parent].reference as u32) {
+                nodes[parent].left_index = index;
+            } else {
+                nodes[parent].right_index = index;
+            }
+        }
+
+        Ok(Self {
+            node_count: names.len() as u32,
+            nodes,
+        })
+    }
+}
+
+impl DictSection {
+    /// Find the texture index for `name` using the same search as the game.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        let index = search_dict(&self.nodes, name)?;
+        // The search always finds a node, so check for a match.
+        (index > 0 && self.nodes[index].name == name).then(|| index - 1)
+    }
+}
+
+impl DictNode {
+    fn child_index(&self, name: &str) -> usize {
+        if name_bit(name, self.reference as u32) {
+            self.right_index as usize
+        } else {
+            self.left_index as usize
+        }
+    }
+}
+
+// Find the index of the node with the closest name to `name`.
+// References always increase, so this visits each node at most once.
+fn search_dict(nodes: &[DictNode], name: &str) -> Option<usize> {
+    let mut parent = nodes.first()?;
+    let mut index = parent.left_index as usize;
+    let mut child = nodes.get(index)?;
+    while parent.reference < child.reference {
+        parent = child;
+        index = child.child_index(name);
+        child = nodes.get(index)?;
+    }
+    Some(index)
+}
+
+// Bits are indexed starting from the least significant bit of the last byte.
+fn name_bit(name: &str, bit: u32) -> bool {
+    let bytes = name.as_bytes();
+    let byte_index = (bit / 8) as usize;
+    byte_index < bytes.len() && (bytes[bytes.len() - byte_index - 1] >> (bit % 8)) & 1 != 0
+}
+
+fn first_different_bit(a: &str, b: &str) -> Option<u32> {
+    let bit_count = a.len().max(b.len()) as u32 * 8;
+    (0..bit_count).find(|i| name_bit(a, *i) != name_bit(b, *i))
+}
+
+// Add the texture index to errors for each BRTI.
+#[binrw::parser(reader, endian)]
+fn parse_brtis(count: u32, options: ReadOptions) -> BinResult<Vec<BrtiOffset>> {
+    let offset = u64::read_options(reader, endian, ())?;
+
+    let saved_pos = reader.stream_position()?;
+    reader.seek(SeekFrom::Start(offset))?;
+    check_count(reader, count as usize, options.max_textures, 8)?;
+    let brtis = (0..count as usize)
+        .map(|index| {
+            BrtiOffset::read_options(reader, endian, ()).map_err(|e| {
+                e.with_context(Section {
+                    magic: *b"BRTI",
+                    index: Some(index),
+                })
+            })
+        })
+        .collect::<BinResult<Vec<_>>>()?;
+    reader.seek(SeekFrom::Start(saved_pos))?;
+
+    Ok(brtis)
+}
+
+// Read `count` items after checking `count` against `limit` and the remaining bytes.
+fn parse_count<T, R>(
+    reader: &mut R,
+    endian: binrw::Endian,
+    (count, limit, min_item_size): (usize, usize, u64),
+) -> BinResult<Vec<T>>
+where
+    T: for<'a> BinRead<Args<'a> = ()> + 'static,
+    R: Read + Seek,
+{
+    check_count(reader, count, limit, min_item_size)?;
+    binrw::helpers::count(count)(reader, endian, ())
+}
+
+// Avoid allocating large amounts of memory for counts from corrupt files.
+fn check_count<R: Read + Seek>(
+    reader: &mut R,
+    count: usize,
+    limit: usize,
+    min_item_size: u64,
+) -> BinResult<()> {
+    let pos = reader.stream_position()?;
+    if count > limit {
+        return Err(binrw::Error::AssertFail {
+            pos,
+            message: format!("count {count} exceeds the limit of {limit}"),
+        });
+    }
+
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(pos))?;
+    let remaining = end.saturating_sub(pos);
+    if (count as u64).saturating_mul(min_item_size) > remaining {
+        return Err(binrw::Error::AssertFail {
+            pos,
+            message: format!("count {count} exceeds the remaining {remaining} bytes"),
+        });
+    }
+    Ok(())
+}
+
+// FilePtr64 panics for offsets that don't fit in an i64.
+fn parse_ptr64<T, R>(reader: &mut R, endian: binrw::Endian, args: T::Args<'_>) -> BinResult<T>
+where
+    T: BinRead,
+    R: Read + Seek,
+{
+    let offset = u64::read_options(reader, endian, ())?;
+
+    let saved_pos = reader.stream_position()?;
+    reader.seek(SeekFrom::Start(offset))?;
+    let value = T::read_options(reader, endian, args)?;
+    reader.seek(SeekFrom::Start(saved_pos))?;
+
+    Ok(value)
+}
+
+#[binrw::parser(reader, endian)]
+fn parse_str_ptr64() -> BinResult<String> {
+    let offset = u64::read_options(reader, endian, ())?;
+    if offset == 0 {
+        return Ok(String::new());
+    }
+
+    let saved_pos = reader.stream_position()?;
+    reader.seek(SeekFrom::Start(offset))?;
+    let value = BntxStr::read_options(reader, endian, ())?;
+    reader.seek(SeekFrom::Start(saved_pos))?;
+
+    Ok(value.chars)
+}
+
+// The file name points to the string chars instead of the length.
+#[binrw::parser(reader, endian)]
+fn parse_str_chars_ptr32() -> BinResult<String> {
+    let offset = u32::read_options(reader, endian, ())?;
+    if offset == 0 {
+        return Ok(String::new());
+    }
+
+    let saved_pos = reader.stream_position()?;
+    reader.seek(SeekFrom::Start(offset as u64))?;
+    let value = NullString::read_options(reader, endian, ())?;
+    reader.seek(SeekFrom::Start(saved_pos))?;
+
+    Ok(value.to_string())
+}
+
+#[binrw::parser(reader)]
+fn stream_position() -> BinResult<u64> {
+    Ok(reader.stream_position()?)
+}
+
 impl SurfaceFormat {
-    fn bytes_per_pixel(&self) -> u32 {
+    /// Create a format from a layout and type.
+    /// Combinations without a named variant use [SurfaceFormat::Unknown].
+    pub fn new(layout: ChannelLayout, channel_type: ChannelType) -> Self {
+        Self::from(((layout as u32) << 8) | channel_type as u32)
+    }
+
+    /// The channel layout or `None` if the layout is not recognized.
+    pub fn layout(&self) -> Option<ChannelLayout> {
+        ChannelLayout::try_from((u32::from(*self) >> 8) as u8).ok()
+    }
+
+    /// The channel type or `None` if the type is not recognized.
+    pub fn channel_type(&self) -> Option<ChannelType> {
+        ChannelType::try_from(u32::from(*self) as u8).ok()
+    }
+
+    pub fn is_srgb(&self) -> bool {
+        self.channel_type() == Some(ChannelType::Srgb)
+    }
+
+    /// Returns `true` for block compressed formats like BC7 or ASTC.
+    pub fn is_compressed(&self) -> bool {
+        self.block_dim()
+            .map(|d| d != BlockDim::uncompressed())
+            .unwrap_or_default()
+    }
+
+    /// The sRGB variant of this format or the format itself if there is no sRGB variant.
+    pub fn to_srgb(&self) -> Self {
+        self.with_channel_type(ChannelType::Unorm, ChannelType::Srgb)
+    }
+
+    /// The UNORM variant of this format or the format itself if there is no UNORM variant.
+    pub fn to_linear(&self) -> Self {
+        self.with_channel_type(ChannelType::Srgb, ChannelType::Unorm)
+    }
+
+    fn with_channel_type(&self, from: ChannelType, to: ChannelType) -> Self {
+        match (self.layout(), self.channel_type()) {
+            (Some(layout), Some(channel_type)) if channel_type == from => {
+                match Self::new(layout, to) {
+                    Self::Unknown(_) => *self,
+                    format => format,
+                }
+            }
+            _ => *self,
+        }
+    }
+
+    pub(crate) fn is_astc(&self) -> bool {
+        self.layout()
+            .map(|l| {
+                (ChannelLayout::Astc4x4 as u8..=ChannelLayout::Astc12x12 as u8).contains(&(l as u8))
+            })
+            .unwrap_or_default()
+    }
+
+    /// The size in bytes of a pixel or compressed block.
+    /// Returns `None` for unknown formats.
+    pub(crate) fn bytes_per_pixel(&self) -> Option<u32> {
         match self {
-            SurfaceFormat::R8Unorm => 1,
-            SurfaceFormat::Unk1 => todo!(),
-            SurfaceFormat::R8G8B8A8Unorm => 4,
-            SurfaceFormat::R8G8B8A8Srgb => 4,
-            SurfaceFormat::B8G8R8A8Unorm => 4,
-            SurfaceFormat::B8G8R8A8Srgb => 4,
-            SurfaceFormat::R11G11B10 => 4,
-            SurfaceFormat::BC1Unorm => 8,
-            SurfaceFormat::BC1Srgb => 8,
-            SurfaceFormat::BC2Unorm => 16,
-            SurfaceFormat::BC2Srgb => 16,
-            SurfaceFormat::BC3Unorm => 16,
-            SurfaceFormat::BC3Srgb => 16,
-            SurfaceFormat::BC4Unorm => 8,
-            SurfaceFormat::BC4Snorm => 8,
-            SurfaceFormat::BC5Unorm => 16,
-            SurfaceFormat::BC5Snorm => 16,
-            SurfaceFormat::BC6Sfloat => 16,
-            SurfaceFormat::BC6Ufloat => 16,
-            SurfaceFormat::BC7Unorm => 16,
-            SurfaceFormat::BC7Srgb => 16,
-        }
-    }
-
-    fn block_dim(&self) -> BlockDim {
+            SurfaceFormat::R4G4Unorm => Some(1),
+            SurfaceFormat::R8Unorm => Some(1),
+            SurfaceFormat::R8Snorm => Some(1),
+            SurfaceFormat::R8Uint => Some(1),
+            SurfaceFormat::R8Sint => Some(1),
+            SurfaceFormat::R4G4B4A4Unorm => Some(2),
+            SurfaceFormat::A4B4G4R4Unorm => Some(2),
+            SurfaceFormat::R5G5B5A1Unorm => Some(2),
+            SurfaceFormat::A1B5G5R5Unorm => Some(2),
+            SurfaceFormat::R5G6B5Unorm => Some(2),
+            SurfaceFormat::B5G6R5Unorm => Some(2),
+            SurfaceFormat::R8G8Unorm => Some(2),
+            SurfaceFormat::R8G8Snorm => Some(2),
+            SurfaceFormat::R8G8Uint => Some(2),
+            SurfaceFormat::R8G8Sint => Some(2),
+            SurfaceFormat::R16Unorm => Some(2),
+            SurfaceFormat::R16Snorm => Some(2),
+            SurfaceFormat::R16Uint => Some(2),
+            SurfaceFormat::R16Sint => Some(2),
+            SurfaceFormat::R16Float => Some(2),
+            SurfaceFormat::R8G8B8A8Unorm => Some(4),
+            SurfaceFormat::R8G8B8A8Snorm => Some(4),
+            SurfaceFormat::R8G8B8A8Uint => Some(4),
+            SurfaceFormat::R8G8B8A8Sint => Some(4),
+            SurfaceFormat::R8G8B8A8Srgb => Some(4),
+            SurfaceFormat::B8G8R8A8Unorm => Some(4),
+            SurfaceFormat::B8G8R8A8Srgb => Some(4),
+            SurfaceFormat::R10G10B10A2Unorm => Some(4),
+            SurfaceFormat::R10G10B10A2Uint => Some(4),
+            SurfaceFormat::R11G11B10 => Some(4),
+            SurfaceFormat::R16G16Unorm => Some(4),
+            SurfaceFormat::R16G16Snorm => Some(4),
+            SurfaceFormat::R16G16Uint => Some(4),
+            SurfaceFormat::R16G16Sint => Some(4),
+            SurfaceFormat::R16G16Float => Some(4),
+            SurfaceFormat::R32Uint => Some(4),
+            SurfaceFormat::R32Sint => Some(4),
+            SurfaceFormat::R32Float => Some(4),
+            SurfaceFormat::R16G16B16A16Unorm => Some(8),
+            SurfaceFormat::R16G16B16A16Snorm => Some(8),
+            SurfaceFormat::R16G16B16A16Uint => Some(8),
+            SurfaceFormat::R16G16B16A16Sint => Some(8),
+            SurfaceFormat::R16G16B16A16Float => Some(8),
+            SurfaceFormat::R32G32Uint => Some(8),
+            SurfaceFormat::R32G32Sint => Some(8),
+            SurfaceFormat::R32G32Float => Some(8),
+            SurfaceFormat::R32G32B32Uint => Some(12),
+            SurfaceFormat::R32G32B32Sint => Some(12),
+            SurfaceFormat::R32G32B32Float => Some(12),
+            SurfaceFormat::R32G32B32A32Uint => Some(16),
+            SurfaceFormat::R32G32B32A32Sint => Some(16),
+            SurfaceFormat::R32G32B32A32Float => Some(16),
+            SurfaceFormat::BC1Unorm => Some(8),
+            SurfaceFormat::BC1Srgb => Some(8),
+            SurfaceFormat::BC2Unorm => Some(16),
+            SurfaceFormat::BC2Srgb => Some(16),
+            SurfaceFormat::BC3Unorm => Some(16),
+            SurfaceFormat::BC3Srgb => Some(16),
+            SurfaceFormat::BC4Unorm => Some(8),
+            SurfaceFormat::BC4Snorm => Some(8),
+            SurfaceFormat::BC5Unorm => Some(16),
+            SurfaceFormat::BC5Snorm => Some(16),
+            SurfaceFormat::BC6Sfloat => Some(16),
+            SurfaceFormat::BC6Ufloat => Some(16),
+            SurfaceFormat::BC7Unorm => Some(16),
+            SurfaceFormat::BC7Srgb => Some(16),
+            SurfaceFormat::Astc4x4Unorm => Some(16),
+            SurfaceFormat::Astc4x4Srgb => Some(16),
+            SurfaceFormat::Astc5x4Unorm => Some(16),
+            SurfaceFormat::Astc5x4Srgb => Some(16),
+            SurfaceFormat::Astc5x5Unorm => Some(16),
+            SurfaceFormat::Astc5x5Srgb => Some(16),
+            SurfaceFormat::Astc6x5Unorm => Some(16),
+            SurfaceFormat::Astc6x5Srgb => Some(16),
+            SurfaceFormat::Astc6x6Unorm => Some(16),
+            SurfaceFormat::Astc6x6Srgb => Some(16),
+            SurfaceFormat::Astc8x5Unorm => Some(16),
+            SurfaceFormat::Astc8x5Srgb => Some(16),
+            SurfaceFormat::Astc8x6Unorm => Some(16),
+            SurfaceFormat::Astc8x6Srgb => Some(16),
+            SurfaceFormat::Astc8x8Unorm => Some(16),
+            SurfaceFormat::Astc8x8Srgb => Some(16),
+            SurfaceFormat::Astc10x5Unorm => Some(16),
+            SurfaceFormat::Astc10x5Srgb => Some(16),
+            SurfaceFormat::Astc10x6Unorm => Some(16),
+            SurfaceFormat::Astc10x6Srgb => Some(16),
+            SurfaceFormat::Astc10x8Unorm => Some(16),
+            SurfaceFormat::Astc10x8Srgb => Some(16),
+            SurfaceFormat::Astc10x10Unorm => Some(16),
+            SurfaceFormat::Astc10x10Srgb => Some(16),
+            SurfaceFormat::Astc12x10Unorm => Some(16),
+            SurfaceFormat::Astc12x10Srgb => Some(16),
+            SurfaceFormat::Astc12x12Unorm => Some(16),
+            SurfaceFormat::Astc12x12Srgb => Some(16),
+            SurfaceFormat::B5G5R5A1Unorm => Some(2),
+            SurfaceFormat::Unknown(_) => None,
+        }
+    }
+
+    /// The dimensions of a compressed block or a single pixel for uncompressed formats.
+    /// Returns `None` for unknown formats.
+    pub(crate) fn block_dim(&self) -> Option<BlockDim> {
         match self {
-            SurfaceFormat::R8Unorm => BlockDim::uncompressed(),
-            SurfaceFormat::Unk1 => todo!(),
-            SurfaceFormat::R8G8B8A8Unorm => BlockDim::uncompressed(),
-            SurfaceFormat::R8G8B8A8Srgb => BlockDim::uncompressed(),
-            SurfaceFormat::B8G8R8A8Unorm => BlockDim::uncompressed(),
-            SurfaceFormat::B8G8R8A8Srgb => BlockDim::uncompressed(),
-            SurfaceFormat::R11G11B10 => BlockDim::uncompressed(),
-            SurfaceFormat::BC1Unorm => BlockDim::block_4x4(),
-            SurfaceFormat::BC1Srgb => BlockDim::block_4x4(),
-            SurfaceFormat::BC2Unorm => BlockDim::block_4x4(),
-            SurfaceFormat::BC2Srgb => BlockDim::block_4x4(),
-            SurfaceFormat::BC3Unorm => BlockDim::block_4x4(),
-            SurfaceFormat::BC3Srgb => BlockDim::block_4x4(),
-            SurfaceFormat::BC4Unorm => BlockDim::block_4x4(),
-            SurfaceFormat::BC4Snorm => BlockDim::block_4x4(),
-            SurfaceFormat::BC5Unorm => BlockDim::block_4x4(),
-            SurfaceFormat::BC5Snorm => BlockDim::block_4x4(),
-            SurfaceFormat::BC6Sfloat => BlockDim::block_4x4(),
-            SurfaceFormat::BC6Ufloat => BlockDim::block_4x4(),
-            SurfaceFormat::BC7Unorm => BlockDim::block_4x4(),
-            SurfaceFormat::BC7Srgb => BlockDim::block_4x4(),
+            SurfaceFormat::R4G4Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8Snorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8Uint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8Sint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R4G4B4A4Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::A4B4G4R4Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R5G5B5A1Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::A1B5G5R5Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R5G6B5Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::B5G6R5Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8G8Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8G8Snorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8G8Uint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8G8Sint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16Snorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16Uint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16Sint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16Float => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8G8B8A8Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8G8B8A8Snorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8G8B8A8Uint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8G8B8A8Sint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R8G8B8A8Srgb => Some(BlockDim::uncompressed()),
+            SurfaceFormat::B8G8R8A8Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::B8G8R8A8Srgb => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R10G10B10A2Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R10G10B10A2Uint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R11G11B10 => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16G16Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16G16Snorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16G16Uint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16G16Sint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16G16Float => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R32Uint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R32Sint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R32Float => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16G16B16A16Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16G16B16A16Snorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16G16B16A16Uint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16G16B16A16Sint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R16G16B16A16Float => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R32G32Uint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R32G32Sint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R32G32Float => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R32G32B32Uint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R32G32B32Sint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R32G32B32Float => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R32G32B32A32Uint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R32G32B32A32Sint => Some(BlockDim::uncompressed()),
+            SurfaceFormat::R32G32B32A32Float => Some(BlockDim::uncompressed()),
+            SurfaceFormat::BC1Unorm => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC1Srgb => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC2Unorm => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC2Srgb => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC3Unorm => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC3Srgb => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC4Unorm => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC4Snorm => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC5Unorm => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC5Snorm => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC6Sfloat => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC6Ufloat => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC7Unorm => Some(BlockDim::block_4x4()),
+            SurfaceFormat::BC7Srgb => Some(BlockDim::block_4x4()),
+            SurfaceFormat::Astc4x4Unorm => Some(astc_block_dim(4, 4)),
+            SurfaceFormat::Astc4x4Srgb => Some(astc_block_dim(4, 4)),
+            SurfaceFormat::Astc5x4Unorm => Some(astc_block_dim(5, 4)),
+            SurfaceFormat::Astc5x4Srgb => Some(astc_block_dim(5, 4)),
+            SurfaceFormat::Astc5x5Unorm => Some(astc_block_dim(5, 5)),
+            SurfaceFormat::Astc5x5Srgb => Some(astc_block_dim(5, 5)),
+            SurfaceFormat::Astc6x5Unorm => Some(astc_block_dim(6, 5)),
+            SurfaceFormat::Astc6x5Srgb => Some(astc_block_dim(6, 5)),
+            SurfaceFormat::Astc6x6Unorm => Some(astc_block_dim(6, 6)),
+            SurfaceFormat::Astc6x6Srgb => Some(astc_block_dim(6, 6)),
+            SurfaceFormat::Astc8x5Unorm => Some(astc_block_dim(8, 5)),
+            SurfaceFormat::Astc8x5Srgb => Some(astc_block_dim(8, 5)),
+            SurfaceFormat::Astc8x6Unorm => Some(astc_block_dim(8, 6)),
+            SurfaceFormat::Astc8x6Srgb => Some(astc_block_dim(8, 6)),
+            SurfaceFormat::Astc8x8Unorm => Some(astc_block_dim(8, 8)),
+            SurfaceFormat::Astc8x8Srgb => Some(astc_block_dim(8, 8)),
+            SurfaceFormat::Astc10x5Unorm => Some(astc_block_dim(10, 5)),
+            SurfaceFormat::Astc10x5Srgb => Some(astc_block_dim(10, 5)),
+            SurfaceFormat::Astc10x6Unorm => Some(astc_block_dim(10, 6)),
+            SurfaceFormat::Astc10x6Srgb => Some(astc_block_dim(10, 6)),
+            SurfaceFormat::Astc10x8Unorm => Some(astc_block_dim(10, 8)),
+            SurfaceFormat::Astc10x8Srgb => Some(astc_block_dim(10, 8)),
+            SurfaceFormat::Astc10x10Unorm => Some(astc_block_dim(10, 10)),
+            SurfaceFormat::Astc10x10Srgb => Some(astc_block_dim(10, 10)),
+            SurfaceFormat::Astc12x10Unorm => Some(astc_block_dim(12, 10)),
+            SurfaceFormat::Astc12x10Srgb => Some(astc_block_dim(12, 10)),
+            SurfaceFormat::Astc12x12Unorm => Some(astc_block_dim(12, 12)),
+            SurfaceFormat::Astc12x12Srgb => Some(astc_block_dim(12, 12)),
+            SurfaceFormat::B5G5R5A1Unorm => Some(BlockDim::uncompressed()),
+            SurfaceFormat::Unknown(_) => None,
         }
     }
 }
 
+fn astc_block_dim(width: u32, height: u32) -> BlockDim {
+    BlockDim {
+        width: NonZeroU32::new(width).unwrap(),
+        height: NonZeroU32::new(height).unwrap(),
+        depth: NonZeroU32::MIN,
+    }
+}
+
 macro_rules! xc3_write_binwrite_impl {
     ($($ty:ty),*) => {
         $(
@@ -600,14 +1950,1672 @@ xc3_write_binwrite_impl!(
     Brtd,
     ByteOrder,
     TextureDimension,
+    TileMode,
     TextureViewDimension,
+    ChannelSelector,
     SurfaceFormat,
     BntxStr,
-    DictSection,
     Mipmaps
 );
 
 #[cfg(test)]
 mod tests {
-    // TODO: test cases?
+    use super::*;
+
+    use std::io::Cursor;
+
+    // RGBA8 data for all layers and mipmaps with a repeating byte pattern.
+    fn rgba8_surface(
+        width: u32,
+        height: u32,
+        depth: u32,
+        layers: u32,
+        mipmaps: u32,
+    ) -> image_dds::Surface<Vec<u8>> {
+        let layer_size: u32 = (0..mipmaps)
+            .map(|i| (width >> i).max(1) * (height >> i).max(1) * (depth >> i).max(1) * 4)
+            .sum();
+        image_dds::Surface {
+            width,
+            height,
+            depth,
+            layers,
+            mipmaps,
+            image_format: image_dds::ImageFormat::Rgba8Unorm,
+            data: (0..layer_size * layers).map(|i| (i % 251) as u8).collect(),
+        }
+    }
+
+    // Write the file and read it again.
+    fn round_trip(bntx: &Bntx) -> Bntx {
+        Bntx::from_bytes(&bntx.to_bytes().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn read_written_texture() {
+        let surface = rgba8_surface(4, 4, 1, 1, 1);
+        let bntx = Bntx::from_surface(surface, "tex").unwrap();
+
+        let bntx = round_trip(&bntx);
+
+        assert_eq!("tex", bntx.name());
+        assert_eq!(1, bntx.textures().len());
+        let texture = bntx.texture_by_name("tex").unwrap();
+        assert_eq!(
+            (4, 4, 1),
+            (texture.width(), texture.height(), texture.depth())
+        );
+        assert_eq!(SurfaceFormat::R8G8B8A8Unorm, texture.image_format());
+        assert_eq!(
+            (0..64).collect::<Vec<u8>>(),
+            texture.deswizzled_data().unwrap()
+        );
+        assert!(bntx.texture(1).is_none());
+    }
+
+    #[test]
+    fn read_written_textures() {
+        let surface = |width, height, mipmaps, image_format, size| image_dds::Surface {
+            width,
+            height,
+            depth: 1,
+            layers: 1,
+            mipmaps,
+            image_format,
+            data: (0..size).map(|i| i as u8).collect::<Vec<_>>(),
+        };
+        let surfaces = [
+            (
+                "a",
+                surface(4, 4, 1, image_dds::ImageFormat::Rgba8Unorm, 64),
+            ),
+            (
+                "b",
+                surface(64, 32, 2, image_dds::ImageFormat::BC7RgbaUnorm, 2048 + 512),
+            ),
+            ("c", surface(8, 8, 1, image_dds::ImageFormat::R8Unorm, 64)),
+        ];
+        let bntx = Bntx::from_surfaces(surfaces.clone(), "textures").unwrap();
+
+        let bntx = round_trip(&bntx);
+
+        assert_eq!("textures", bntx.name());
+        assert_eq!(3, bntx.textures().len());
+        assert_eq!(
+            dict_nodes(&DictSection::from_names(&["a", "b", "c"]).unwrap()),
+            dict_nodes(&bntx.nx_header.dict)
+        );
+        assert_eq!(Some(1), bntx.find_texture("b"));
+        assert_eq!(None, bntx.find_texture("d"));
+        for (texture, (name, surface)) in bntx.textures().zip(surfaces) {
+            assert_eq!(name, texture.name());
+            assert_eq!(surface.width, texture.width());
+            assert_eq!(surface.mipmaps, texture.mipmap_count());
+            assert_eq!(surface.data, texture.deswizzled_data().unwrap());
+        }
+    }
+
+    #[test]
+    fn relocation_entries_single_texture() {
+        // NX header, BRTI offsets, _DIC names, BRTI pointers.
+        let pointers = [40, 56, 64, 408, 480, 496, 600, 608, 616, 632, 640];
+        assert_eq!(
+            vec![
+                RelocationEntry {
+                    position: 40,
+                    struct_count: 2,
+                    offset_count: 1,
+                    padding_count: 45
+                },
+                RelocationEntry {
+                    position: 56,
+                    struct_count: 2,
+                    offset_count: 2,
+                    padding_count: 70
+                },
+                RelocationEntry {
+                    position: 480,
+                    struct_count: 2,
+                    offset_count: 1,
+                    padding_count: 1
+                },
+                RelocationEntry {
+                    position: 600,
+                    struct_count: 1,
+                    offset_count: 3,
+                    padding_count: 0
+                },
+            ],
+            relocation_entries(&pointers)
+        );
+    }
+
+    #[test]
+    fn relocation_entries_long_run() {
+        let pointers: Vec<_> = (0..600).map(|i| 8 * i).collect();
+        assert_eq!(
+            vec![
+                RelocationEntry {
+                    position: 0,
+                    struct_count: 2,
+                    offset_count: 255,
+                    padding_count: 0
+                },
+                RelocationEntry {
+                    position: 4080,
+                    struct_count: 1,
+                    offset_count: 90,
+                    padding_count: 0
+                },
+            ],
+            relocation_entries(&pointers)
+        );
+    }
+
+    fn dict_nodes(dict: &DictSection) -> Vec<(i32, u16, u16, &str)> {
+        dict.nodes
+            .iter()
+            .map(|n| (n.reference, n.left_index, n.right_index, n.name.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn read_written_renamed_textures() {
+        let surfaces = [
+            ("a", rgba8_surface(4, 4, 1, 1, 1)),
+            ("b", rgba8_surface(8, 8, 1, 1, 1)),
+        ];
+        let mut bntx = Bntx::from_surfaces(surfaces, "file").unwrap();
+        bntx.header.name = "renamed".to_string();
+        bntx.nx_header.brtis[1].brti.name = "c".to_string();
+
+        let new_bntx = round_trip(&bntx);
+
+        assert_eq!("renamed", new_bntx.name());
+        assert_eq!(
+            vec!["a", "c"],
+            new_bntx.textures().map(|t| t.name()).collect::<Vec<_>>()
+        );
+        assert_eq!(Some(1), new_bntx.find_texture("c"));
+        assert_eq!(None, new_bntx.find_texture("b"));
+        assert_eq!(
+            dict_nodes(&DictSection::from_names(&["a", "c"]).unwrap()),
+            dict_nodes(&new_bntx.nx_header.dict)
+        );
+        assert_eq!(
+            bntx.nx_header.brtd.image_data,
+            new_bntx.nx_header.brtd.image_data
+        );
+    }
+
+    #[test]
+    fn write_renamed_duplicate_texture() {
+        let surfaces = [
+            ("a", rgba8_surface(4, 4, 1, 1, 1)),
+            ("b", rgba8_surface(4, 4, 1, 1, 1)),
+        ];
+        let mut bntx = Bntx::from_surfaces(surfaces, "file").unwrap();
+        bntx.nx_header.brtis[1].brti.name = "a".to_string();
+
+        assert!(matches!(
+            bntx.to_bytes(),
+            Err(Error::Create {
+                source: surface::CreateBntxError::Dict(CreateDictError::DuplicateName(_)),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn dict_from_names() {
+        // "b" is closest to the root, so it splits on its lowest set bit.
+        let dict = DictSection::from_names(&["a", "b"]).unwrap();
+        assert_eq!(2, dict.node_count);
+        assert_eq!(
+            vec![(-1, 1, 0, ""), (0, 2, 1, "a"), (1, 0, 2, "b")],
+            dict_nodes(&dict)
+        );
+    }
+
+    #[test]
+    fn dict_from_names_duplicate() {
+        assert_eq!(
+            Err(CreateDictError::DuplicateName("a".to_string())),
+            DictSection::from_names(&["a", "b", "a"])
+        );
+    }
+
+    #[test]
+    fn dict_from_names_empty() {
+        assert_eq!(
+            Err(CreateDictError::EmptyName),
+            DictSection::from_names(&["a", ""])
+        );
+    }
+
+    #[test]
+    fn dict_from_names_too_many() {
+        let names: Vec<_> = (0..=u16::MAX as usize).map(|i| i.to_string()).collect();
+        let names: Vec<_> = names.iter().map(String::as_str).collect();
+        assert_eq!(
+            Err(CreateDictError::TooManyNames(65536)),
+            DictSection::from_names(&names)
+        );
+        assert_eq!(
+            u16::MAX as usize + 1,
+            DictSection::from_names(&names[1..]).unwrap().nodes.len()
+        );
+    }
+
+    #[test]
+    fn dict_from_names_indistinguishable() {
+        assert_eq!(
+            Err(CreateDictError::IndistinguishableNames(
+                "\0a".to_string(),
+                "a".to_string()
+            )),
+            DictSection::from_names(&["a", "\0a"])
+        );
+    }
+
+    #[test]
+    fn dict_from_names_matches_hand_built_dict() {
+        // Worked out by hand from the Patricia tree insertion rules for ResDic
+        // instead of generated by this crate.
+        // No dictionaries from retail files are covered by the tests.
+        // "c0" splits from the root on bit 4 of '0'.
+        // "c1" differs from "c0" in bit 0 and becomes the root's child.
+        // "n0" differs from "c0" in bit 8 ('n' ^ 'c' == 0x0D) and replaces the back edge of "c0".
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"_DIC");
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        let nodes = [
+            (-1i32, 2u16, 0u16, 0u64),
+            (4, 0, 3, 72),
+            (0, 1, 2, 76),
+            (8, 3, 1, 80),
+        ];
+        for (reference, left_index, right_index, name_offset) in nodes {
+            bytes.extend_from_slice(&reference.to_le_bytes());
+            bytes.extend_from_slice(&left_index.to_le_bytes());
+            bytes.extend_from_slice(&right_index.to_le_bytes());
+            bytes.extend_from_slice(&name_offset.to_le_bytes());
+        }
+        for name in ["c0", "c1", "n0"] {
+            bytes.extend_from_slice(&2u16.to_le_bytes());
+            bytes.extend_from_slice(name.as_bytes());
+        }
+
+        let dict =
+            DictSection::read_le_args(&mut Cursor::new(bytes), (ReadOptions::default(),)).unwrap();
+        assert_eq!(
+            vec![
+                (-1, 2, 0, ""),
+                (4, 0, 3, "c0"),
+                (0, 1, 2, "c1"),
+                (8, 3, 1, "n0")
+            ],
+            dict_nodes(&dict)
+        );
+        assert_eq!(
+            dict_nodes(&dict),
+            dict_nodes(&DictSection::from_names(&["c0", "c1", "n0"]).unwrap())
+        );
+    }
+
+    #[test]
+    fn from_surfaces_empty_name() {
+        let result = Bntx::from_surfaces([("", rgba8_surface(4, 4, 1, 1, 1))], "file");
+        assert!(matches!(
+            result,
+            Err(Error::Create {
+                context: ErrorContext {
+                    texture_index: Some(0),
+                    ..
+                },
+                source: surface::CreateBntxError::Dict(CreateDictError::EmptyName),
+            })
+        ));
+    }
+
+    #[test]
+    fn dict_find() {
+        let names: Vec<_> = (0..300)
+            .map(|i| format!("tex_{}_{i}", i * 7919 % 1000))
+            .collect();
+        let names: Vec<_> = names.iter().map(|n| n.as_str()).collect();
+        let dict = DictSection::from_names(&names).unwrap();
+        for (i, name) in names.iter().enumerate() {
+            assert_eq!(Some(i), dict.find(name));
+        }
+        assert_eq!(None, dict.find("tex"));
+        assert_eq!(None, dict.find(""));
+    }
+
+    #[test]
+    fn dict_find_invalid_index() {
+        let mut dict = DictSection::from_names(&["a"]).unwrap();
+        dict.nodes[1].right_index = 5;
+        assert_eq!(None, dict.find("a"));
+    }
+
+    #[test]
+    fn read_written_texture_big_endian() {
+        let surface = rgba8_surface(4, 4, 1, 1, 1);
+        let mut bntx = Bntx::from_surface(surface, "tex").unwrap();
+        bntx.bom = ByteOrder::BigEndian;
+
+        let mut writer = Cursor::new(Vec::new());
+        bntx.write(&mut writer).unwrap();
+        assert_eq!(&[0xFE, 0xFF], &writer.get_ref()[12..14]);
+
+        writer.set_position(0);
+        let bntx = read_bntx(&mut writer, ReadOptions::default()).unwrap();
+        assert_eq!(ByteOrder::BigEndian, bntx.bom);
+        assert_eq!((0, 4), bntx.version);
+
+        let texture = bntx.texture(0).unwrap();
+        assert_eq!("tex", texture.name());
+        assert_eq!(4, texture.width());
+        assert_eq!(
+            (0..64).collect::<Vec<u8>>(),
+            texture.deswizzled_data().unwrap()
+        );
+
+        // Writing again should preserve the byte order.
+        let mut writer2 = Cursor::new(Vec::new());
+        bntx.write(&mut writer2).unwrap();
+        assert_eq!(writer.into_inner(), writer2.into_inner());
+    }
+
+    #[test]
+    fn read_write_bytes() {
+        let surface = rgba8_surface(4, 4, 1, 1, 1);
+        let bntx = Bntx::from_surface(surface, "tex").unwrap();
+        let bytes = bntx.to_bytes().unwrap();
+        assert_eq!(b"BNTX", &bytes[..4]);
+
+        let bntx = Bntx::from_bytes(&bytes).unwrap();
+        assert_eq!(bytes, bntx.to_bytes().unwrap());
+        assert_eq!(bntx, Bntx::read(&mut Cursor::new(&bytes)).unwrap());
+        assert!(matches!(
+            Bntx::from_bytes(&bytes[..8]),
+            Err(Error::Read { .. })
+        ));
+    }
+
+    #[test]
+    fn read_embedded_bytes() {
+        let surface = rgba8_surface(4, 4, 1, 1, 1);
+        let bytes = Bntx::from_surface(surface, "tex")
+            .unwrap()
+            .to_bytes()
+            .unwrap();
+
+        // Files in archives start after other data.
+        let mut archive = vec![0xAA; 100];
+        archive.extend_from_slice(&bytes);
+        archive.extend_from_slice(&[0xBB; 16]);
+        let mut reader = Cursor::new(&archive);
+        reader.set_position(100);
+
+        let embedded = Bntx::read(&mut reader).unwrap();
+        assert_eq!(Bntx::from_bytes(&bytes).unwrap(), embedded);
+        assert_eq!(bytes, embedded.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn read_written_float_texture() {
+        let data: Vec<u8> = (0..4 * 4 * 8).map(|i| i as u8).collect();
+        let surface = image_dds::Surface {
+            width: 4,
+            height: 4,
+            depth: 1,
+            layers: 1,
+            mipmaps: 1,
+            image_format: image_dds::ImageFormat::Rgba16Float,
+            data: data.clone(),
+        };
+        let bntx = Bntx::from_surface(surface, "tex").unwrap();
+
+        let bntx = round_trip(&bntx);
+        let surface = bntx.to_surface().unwrap();
+        assert_eq!(Some(SurfaceFormat::R16G16B16A16Float), bntx.image_format());
+        assert_eq!(0x1505, u32::from(bntx.image_format().unwrap()));
+        assert_eq!(image_dds::ImageFormat::Rgba16Float, surface.image_format);
+        assert_eq!(data, surface.data);
+    }
+
+    #[test]
+    fn read_written_bgr5a1_texture() {
+        let data: Vec<u8> = (0..4 * 4 * 2).map(|i| i as u8).collect();
+        let surface = image_dds::Surface {
+            width: 4,
+            height: 4,
+            depth: 1,
+            layers: 1,
+            mipmaps: 1,
+            image_format: image_dds::ImageFormat::Bgr5A1Unorm,
+            data: data.clone(),
+        };
+        let bntx = Bntx::from_surface(surface, "tex").unwrap();
+
+        let bntx = round_trip(&bntx);
+        let surface = bntx.to_surface().unwrap();
+        assert_eq!(Some(SurfaceFormat::B5G5R5A1Unorm), bntx.image_format());
+        assert_eq!(image_dds::ImageFormat::Bgr5A1Unorm, surface.image_format);
+        assert_eq!(data, surface.data);
+    }
+
+    #[test]
+    fn bgra4_unsupported() {
+        assert!(matches!(
+            SurfaceFormat::try_from(image_dds::ImageFormat::Bgra4Unorm),
+            Err(surface::CreateBntxError::UnsupportedImageFormat(
+                image_dds::ImageFormat::Bgra4Unorm
+            ))
+        ));
+    }
+
+    #[test]
+    fn deswizzle_astc_texture() {
+        let surface = rgba8_surface(32, 32, 1, 1, 1);
+        let mut bntx = Bntx::from_surface(surface, "tex").unwrap();
+
+        // 10x6 blocks of 16 bytes each.
+        let brti = &mut bntx.nx_header.brtis[0].brti;
+        brti.image_format = SurfaceFormat::Astc8x5Srgb;
+        brti.width = 80;
+        brti.height = 30;
+        assert_eq!(0x3206, u32::from(brti.image_format));
+
+        assert_eq!(10 * 6 * 16, bntx.deswizzled_data().unwrap().len());
+    }
+
+    fn astc_texture(block: u128, image_format: SurfaceFormat) -> Bntx {
+        // The first block is the start of the first row of pixels.
+        let mut surface = rgba8_surface(4, 4, 1, 1, 1);
+        surface.data[..16].copy_from_slice(&block.to_le_bytes());
+        let mut bntx = Bntx::from_surface(surface, "tex").unwrap();
+        // Use a single block of the new format.
+        let dim = image_format.block_dim().unwrap();
+        let brti = &mut bntx.nx_header.brtis[0].brti;
+        brti.image_format = image_format;
+        brti.width = dim.width.get();
+        brti.height = dim.height.get();
+        bntx
+    }
+
+    fn astc_grey(block: u128, image_format: SurfaceFormat) -> Vec<u8> {
+        let surface = astc_texture(block, image_format).to_surface().unwrap();
+        assert_eq!(image_dds::ImageFormat::Rgba8Unorm, surface.image_format);
+        // Grey blocks only need to check the luminance.
+        surface
+            .data
+            .chunks_exact(4)
+            .map(|p| {
+                assert_eq!([p[0], p[0], p[0], 255], p);
+                p[0]
+            })
+            .collect()
+    }
+
+    fn astc_hdr(block: u128, image_format: SurfaceFormat) -> Vec<[u16; 4]> {
+        let surface = astc_texture(block, image_format).to_surface().unwrap();
+        assert_eq!(image_dds::ImageFormat::Rgba16Float, surface.image_format);
+        surface
+            .data
+            .chunks_exact(8)
+            .map(|p| {
+                let c = |i: usize| u16::from_le_bytes([p[i], p[i + 1]]);
+                [c(0), c(2), c(4), c(6)]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decode_astc_void_extent() {
+        let color = 0xFFFF_0000_8000_FFFFu128;
+        let block = 0x1FC | (0x3 << 10) | (0xF_FFFF_FFFF_FFFF << 12) | (color << 64);
+        let bntx = astc_texture(block, SurfaceFormat::Astc4x4Unorm);
+
+        let surface = bntx.to_surface().unwrap();
+        assert_eq!(image_dds::ImageFormat::Rgba8Unorm, surface.image_format);
+        assert_eq!([255, 128, 0, 255].repeat(16), surface.data);
+    }
+
+    #[test]
+    fn decode_astc_void_extent_hdr() {
+        let color = 0x3C00_3800_4000_3C00u128;
+        let block = 0x3FC | (0x3 << 10) | (0xF_FFFF_FFFF_FFFF << 12) | (color << 64);
+        let bntx = astc_texture(block, SurfaceFormat::Astc4x4Unorm);
+
+        let surface = bntx.to_surface().unwrap();
+        assert_eq!(image_dds::ImageFormat::Rgba16Float, surface.image_format);
+        assert_eq!(
+            [0x00, 0x3C, 0x00, 0x40, 0x00, 0x38, 0x00, 0x3C].repeat(16),
+            surface.data
+        );
+    }
+
+    #[test]
+    fn decode_astc_rgb_direct() {
+        // A 4x4 grid of 2-bit weights with a single partition of LDR RGB endpoints.
+        let mut block = 0x42u128 | (8 << 13);
+        for (i, v) in [10u128, 200, 20, 100, 30, 50].iter().enumerate() {
+            block |= v << (17 + i * 8);
+        }
+        // Weights are stored in reverse from the last bit.
+        // Odd texels use the largest weight.
+        for i in (1..16).step_by(2) {
+            block |= 0b11 << (126 - i * 2);
+        }
+
+        for format in [SurfaceFormat::Astc4x4Unorm, SurfaceFormat::Astc4x4Srgb] {
+            let bntx = astc_texture(block, format);
+            let surface = bntx.to_surface().unwrap();
+            assert_eq!([10, 20, 30, 255, 200, 100, 50, 255].repeat(8), surface.data);
+        }
+        let surface = astc_texture(block, SurfaceFormat::Astc4x4Srgb)
+            .to_surface()
+            .unwrap();
+        assert_eq!(image_dds::ImageFormat::Rgba8UnormSrgb, surface.image_format);
+    }
+
+    // The reference blocks below were assembled bit by bit from the ASTC
+    // chapter of the Khronos Data Format Specification. Their expected texels
+    // were worked out from the specification's decoding procedure without
+    // using this decoder. Blocks not described otherwise use single partition
+    // luminance endpoints of 0 and 255, so texels show the decoded weights.
+
+    #[test]
+    fn decode_astc_trits() {
+        // A 4x4 grid of 6 level weights packed as trits with one extra bit.
+        // The trit blocks 0xFF, 0x01, 0x62 and 0x01 cover every decoding case.
+        // This also uses 8 bit endpoint values at the start of the block.
+        assert_eq!(
+            vec![100, 207, 100, 155, 100, 207, 0, 255, 0, 255, 100, 255, 0, 255, 100, 207],
+            astc_grey(
+                0x7dee108c3980000000000001fe000043,
+                SurfaceFormat::Astc4x4Unorm
+            )
+        );
+    }
+
+    #[test]
+    fn decode_astc_quints() {
+        // A 4x4 grid of 10 level weights packed as quints with one extra bit.
+        // The quint blocks 0x06, 0x0D, 0x53, 0x26, 0x02 and 0x01 cover every decoding case.
+        assert_eq!(
+            vec![143, 112, 0, 227, 112, 0, 171, 56, 56, 143, 0, 112, 199, 0, 0, 227],
+            astc_grey(
+                0xb0350e26c2a0300000000001fe000241,
+                SurfaceFormat::Astc4x4Unorm
+            )
+        );
+    }
+
+    #[test]
+    fn decode_astc_dual_plane_quint_endpoints() {
+        // A 4x4 grid of 3-bit weights for two planes with red as the second plane.
+        // The remaining 13 bits store the luminance endpoints 0 and 130 as 80 level quints.
+        // The first plane uses the largest weight and the second plane counts up.
+        let bntx = astc_texture(
+            0xe3cebee7deffe3cebee7deff0fa00453,
+            SurfaceFormat::Astc4x4Unorm,
+        );
+        let surface = bntx.to_surface().unwrap();
+        let expected: Vec<_> = [0, 18, 36, 55, 75, 93, 112, 130]
+            .repeat(2)
+            .into_iter()
+            .flat_map(|r| [r, 130, 130, 255])
+            .collect();
+        assert_eq!(expected, surface.data);
+    }
+
+    #[test]
+    fn decode_astc_two_partitions() {
+        // Partition seed 547 on a small block with black and white partitions.
+        assert_eq!(
+            vec![255, 0, 255, 0, 0, 0, 255, 0, 0, 255, 0, 0, 0, 255, 0, 255],
+            astc_grey(0x1fffe00000446842, SurfaceFormat::Astc4x4Unorm)
+        );
+    }
+
+    #[test]
+    fn decode_astc_three_partitions() {
+        // Partition seed 17 on a 6x6 block with partitions of 0, 128 and 255.
+        // Blocks with at least 31 texels don't scale the texel coordinates.
+        assert_eq!(
+            vec![
+                255, 255, 255, 0, 128, 128, //
+                255, 255, 255, 0, 128, 255, //
+                255, 255, 0, 0, 128, 255, //
+                255, 255, 0, 128, 128, 255, //
+                255, 0, 0, 128, 128, 255, //
+                255, 0, 0, 128, 255, 255, //
+            ],
+            astc_grey(0x1ffff010000000023042, SurfaceFormat::Astc6x6Unorm)
+        );
+    }
+
+    #[test]
+    fn decode_astc_weight_infill() {
+        // A 4x4 grid of 4-bit weights interpolated to a 6x6 block.
+        assert_eq!(
+            vec![
+                0, 52, 100, 155, 203, 255, //
+                159, 147, 131, 124, 108, 96, //
+                207, 199, 159, 96, 60, 48, //
+                48, 163, 203, 52, 92, 207, //
+                32, 128, 163, 92, 128, 223, //
+                84, 84, 100, 155, 171, 171, //
+            ],
+            astc_grey(
+                0x0a5ff5a00f0faa5500000001fe000242,
+                SurfaceFormat::Astc6x6Unorm
+            )
+        );
+    }
+
+    #[test]
+    fn decode_astc_hdr_luminance() {
+        // HDR luminance endpoints 1.0 and 2.0 with a 4x4 grid of 3-bit weights.
+        // The weights count up to test each range of the LNS to half float conversion.
+        // Swapping the endpoint values selects the other large range encoding.
+        for (block, expected) in [
+            (
+                0x11635f11635f00000000000100f04053,
+                [
+                    0x3c00, 0x3c6c, 0x3ce0, 0x3d70, 0x3e10, 0x3ea0, 0x3f4c, 0x4000,
+                ],
+            ),
+            (
+                0x11635f11635f000000000000f1004053,
+                [
+                    0x3c30, 0x3c8e, 0x3cfc, 0x3d7a, 0x3e06, 0x3e84, 0x3f12, 0x3fb0,
+                ],
+            ),
+        ] {
+            let expected: Vec<_> = expected.iter().map(|&l| [l, l, l, 0x3c00]).collect();
+            assert_eq!(
+                expected.repeat(2),
+                astc_hdr(block, SurfaceFormat::Astc4x4Unorm)
+            );
+        }
+    }
+
+    #[test]
+    fn decode_astc_hdr_rgb() {
+        // HDR RGB endpoints using mode 0 with red as the major component.
+        // The values 100, 5, 2, 4, 3, 1 decode to (760, 720, 720) and (800, 784, 768).
+        let expected = [
+            [0x17b0, 0x1640, 0x1640, 0x3c00],
+            [0x17e8, 0x1688, 0x1676, 0x3c00],
+            [0x1813, 0x16d4, 0x16ac, 0x3c00],
+            [0x1835, 0x172e, 0x16ea, 0x3c00],
+            [0x185a, 0x1792, 0x1735, 0x3c00],
+            [0x187c, 0x17ec, 0x1779, 0x3c00],
+            [0x189e, 0x182a, 0x17bc, 0x3c00],
+            [0x18c0, 0x1860, 0x1800, 0x3c00],
+        ];
+        assert_eq!(
+            expected.repeat(2),
+            astc_hdr(
+                0x11635f11635f0000020608040ac96053,
+                SurfaceFormat::Astc4x4Unorm
+            )
+        );
+    }
+
+    #[test]
+    fn surface_format_layout_type() {
+        let format = SurfaceFormat::R8G8B8A8Srgb;
+        assert_eq!(Some(ChannelLayout::R8G8B8A8), format.layout());
+        assert_eq!(Some(ChannelType::Srgb), format.channel_type());
+        assert!(format.is_srgb());
+        assert!(!format.is_compressed());
+        assert_eq!(SurfaceFormat::R8G8B8A8Unorm, format.to_linear());
+
+        assert_eq!(
+            SurfaceFormat::Astc6x5Srgb,
+            SurfaceFormat::new(ChannelLayout::Astc6x5, ChannelType::Srgb)
+        );
+        assert!(SurfaceFormat::Astc6x5Srgb.is_compressed());
+        assert_eq!(SurfaceFormat::BC7Srgb, SurfaceFormat::BC7Unorm.to_srgb());
+
+        // Formats without an sRGB variant are unchanged.
+        assert_eq!(SurfaceFormat::BC4Snorm, SurfaceFormat::BC4Snorm.to_srgb());
+        assert_eq!(SurfaceFormat::R16Unorm, SurfaceFormat::R16Unorm.to_srgb());
+
+        assert_eq!(
+            SurfaceFormat::Unknown(0x1401),
+            SurfaceFormat::new(ChannelLayout::R32, ChannelType::Unorm)
+        );
+        assert_eq!(None, SurfaceFormat::Unknown(0xff01).layout());
+    }
+
+    #[test]
+    fn apply_channel_selector() {
+        assert_eq!(84148994, u32::from(ChannelSelector::default()));
+
+        let mut surface = rgba8_surface(4, 4, 1, 1, 1);
+        surface.data = [10, 20, 30, 40].repeat(16);
+        let data = surface.data.clone();
+        let mut bntx = Bntx::from_surface(surface, "tex").unwrap();
+        let selector = ChannelSelector {
+            r: ChannelSource::R,
+            g: ChannelSource::R,
+            b: ChannelSource::R,
+            a: ChannelSource::One,
+        };
+        bntx.nx_header.brtis[0].brti.comp_sel = selector;
+
+        let bntx = round_trip(&bntx);
+
+        let texture = bntx.texture(0).unwrap();
+        assert_eq!(selector, texture.brti().comp_sel);
+        assert_eq!(data, texture.to_surface_rgba8(false).unwrap().data);
+        assert_eq!(
+            [10, 10, 10, 255].repeat(16),
+            texture.to_surface_rgba8(true).unwrap().data
+        );
+
+        assert_eq!(data, texture.to_surface().unwrap().data);
+        let options = surface::SurfaceOptions {
+            apply_channel_selector: true,
+        };
+        let surface = texture.to_surface_with_options(options).unwrap();
+        assert_eq!(image_dds::ImageFormat::Rgba8Unorm, surface.image_format);
+        assert_eq!([10, 10, 10, 255].repeat(16), surface.data);
+
+        let dds = texture.to_dds_with_options(options).unwrap();
+        assert_eq!(
+            [10, 10, 10, 255].repeat(16),
+            image_dds::Surface::from_dds(&dds).unwrap().data
+        );
+        let dds = texture.to_dds().unwrap();
+        assert_eq!(data, image_dds::Surface::from_dds(&dds).unwrap().data);
+    }
+
+    #[test]
+    fn read_written_unknown_channel_source() {
+        let mut bntx = Bntx::from_surface(rgba8_surface(4, 4, 1, 1, 1), "tex").unwrap();
+        bntx.nx_header.brtis[0].brti.comp_sel = ChannelSelector::from(0x050403ff);
+
+        let bntx = round_trip(&bntx);
+
+        let texture = bntx.texture(0).unwrap();
+        let selector = texture.brti().comp_sel;
+        assert_eq!(ChannelSource::Unknown(0xff), selector.r);
+        assert_eq!(ChannelSource::G, selector.g);
+        assert_eq!(0x050403ff, u32::from(selector));
+        assert!(texture.to_surface_rgba8(false).is_ok());
+        assert!(matches!(
+            texture.to_surface_rgba8(true),
+            Err(surface::CreateSurfaceError::UnknownChannelSelector(_))
+        ));
+        let options = surface::SurfaceOptions {
+            apply_channel_selector: true,
+        };
+        assert!(matches!(
+            texture.to_dds_with_options(options),
+            Err(surface::CreateDdsError::Surface(
+                surface::CreateSurfaceError::UnknownChannelSelector(_)
+            ))
+        ));
+    }
+
+    #[test]
+    fn read_written_custom_block_height() {
+        let surface = rgba8_surface(64, 64, 1, 1, 1);
+        let data = surface.data.clone();
+        let options = surface::SwizzleOptions {
+            block_height: Some(tegra_swizzle::BlockHeight::One),
+            ..Default::default()
+        };
+        let bntx = Bntx::from_surface_with_options(surface, "tex", options).unwrap();
+
+        let bntx = round_trip(&bntx);
+
+        let texture = bntx.texture(0).unwrap();
+        assert_eq!(0, texture.brti().block_height_log2);
+        assert_eq!(data, texture.deswizzled_data().unwrap());
+        assert!(matches!(
+            texture.deswizzled_data_strict(),
+            Err(surface::CreateSurfaceError::BlockHeightMismatch {
+                block_height: tegra_swizzle::BlockHeight::One,
+                inferred: tegra_swizzle::BlockHeight::Eight,
+            })
+        ));
+    }
+
+    #[test]
+    fn read_written_textures_with_options() {
+        let surfaces = [
+            (
+                "a",
+                rgba8_surface(64, 64, 1, 1, 1),
+                surface::SwizzleOptions {
+                    block_height: Some(tegra_swizzle::BlockHeight::One),
+                    ..Default::default()
+                },
+            ),
+            (
+                "b",
+                rgba8_surface(8, 8, 1, 1, 1),
+                surface::SwizzleOptions {
+                    tile_mode: TileMode::Linear,
+                    ..Default::default()
+                },
+            ),
+            (
+                "c",
+                rgba8_surface(16, 16, 1, 1, 1),
+                surface::SwizzleOptions::default(),
+            ),
+        ];
+        let bntx = Bntx::from_surfaces_with_options(surfaces.clone(), "file").unwrap();
+
+        let bntx = round_trip(&bntx);
+
+        let textures: Vec<_> = bntx.textures().collect();
+        assert_eq!(0, textures[0].brti().block_height_log2);
+        assert_eq!(TileMode::Linear, textures[1].tile_mode());
+        assert_eq!(TileMode::BlockLinear, textures[2].tile_mode());
+        for (texture, (_, surface, _)) in textures.iter().zip(surfaces) {
+            assert_eq!(surface.data, texture.deswizzled_data().unwrap());
+        }
+    }
+
+    #[test]
+    fn read_written_linear_texture() {
+        // Two layers with mipmaps of 20x6 and 10x3 pixels.
+        let data: Vec<u8> = (0..300).map(|i| i as u8).collect();
+        let surface = image_dds::Surface {
+            width: 20,
+            height: 6,
+            depth: 1,
+            layers: 2,
+            mipmaps: 2,
+            image_format: image_dds::ImageFormat::R8Unorm,
+            data: data.clone(),
+        };
+        let options = surface::SwizzleOptions {
+            tile_mode: TileMode::Linear,
+            ..Default::default()
+        };
+        let bntx = Bntx::from_surface_with_options(surface, "tex", options).unwrap();
+
+        let bntx = round_trip(&bntx);
+
+        let texture = bntx.texture(0).unwrap();
+        assert_eq!(TileMode::Linear, texture.tile_mode());
+        // Each row is padded to 32 bytes.
+        assert_eq!((32 * 6 + 32 * 3) * 2, texture.brti().image_size);
+        let offsets = &texture.brti().mipmaps.mipmap_offsets;
+        assert_eq!(32 * 6, offsets[1] - offsets[0]);
+        assert_eq!(data, texture.deswizzled_data().unwrap());
+        assert_eq!(data, texture.deswizzled_data_strict().unwrap());
+        assert_eq!(data[270..300], texture.mip(1, 1).unwrap().data);
+    }
+
+    #[test]
+    fn read_written_texture_mips() {
+        // Two layers with mipmaps of 64x64, 32x32, and 16x16 pixels.
+        let surface = rgba8_surface(64, 64, 1, 2, 3);
+        let data = surface.data.clone();
+        let bntx = Bntx::from_surface(surface, "tex").unwrap();
+
+        let texture = bntx.texture(0).unwrap();
+        let mut offset = 0;
+        for layer in 0..2 {
+            for (level, size) in [64, 32, 16].iter().copied().enumerate() {
+                let mip = texture.mip(layer, level as u32).unwrap();
+                assert_eq!((size, size, 1), (mip.width, mip.height, mip.depth));
+                let len = (size * size * 4) as usize;
+                assert_eq!(data[offset..offset + len], mip.data);
+                offset += len;
+            }
+        }
+        assert!(matches!(
+            texture.mip(2, 0),
+            Err(surface::CreateSurfaceError::MipOutOfRange { layer: 2, level: 0 })
+        ));
+    }
+
+    #[test]
+    fn deswizzle_padded_mipmaps() {
+        // Mipmaps of 64x64 and 32x32 pixels.
+        let surface = rgba8_surface(64, 64, 1, 1, 2);
+        let data = surface.data.clone();
+        let bntx = Bntx::from_surface(surface, "tex").unwrap();
+
+        let mut bntx = round_trip(&bntx);
+
+        // Add padding between the mipmaps.
+        let brtd = &mut bntx.nx_header.brtd;
+        let brti = &mut bntx.nx_header.brtis[0].brti;
+        let mip1 = (brti.mipmaps.mipmap_offsets[1] - brtd.data_offset) as usize;
+        brtd.image_data.splice(mip1..mip1, [0xff; 512]);
+        brti.mipmaps.mipmap_offsets[1] += 512;
+        brti.image_size += 512;
+        assert_eq!(data, bntx.texture(0).unwrap().deswizzled_data().unwrap());
+
+        bntx.nx_header.brtis[0].brti.mipmaps.mipmap_offsets[1] += 0x10000;
+        assert!(matches!(
+            bntx.texture(0).unwrap().deswizzled_data(),
+            Err(surface::CreateSurfaceError::InvalidMipmapOffset { level: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_dimensions() {
+        // The product of the dimensions is 0, but width * 4 overflows when swizzling.
+        let mut bntx = Bntx::from_surface(rgba8_surface(4, 4, 1, 1, 1), "tex").unwrap();
+        let brti = &mut bntx.nx_header.brtis[0].brti;
+        brti.width = 2259216897;
+        brti.height = 0;
+
+        let texture = bntx.texture(0).unwrap();
+        let invalid = |result| {
+            matches!(
+                result,
+                Err(surface::CreateSurfaceError::SwizzleError(
+                    tegra_swizzle::SwizzleError::InvalidSurface { .. }
+                ))
+            )
+        };
+        assert!(invalid(texture.to_surface().map(|_| ())));
+        assert!(invalid(texture.mip(0, 0).map(|_| ())));
+        assert!(invalid(texture.deswizzled_data_strict().map(|_| ())));
+        assert!(invalid(texture.to_surface_rgba8(true).map(|_| ())));
+    }
+
+    #[test]
+    fn invalid_dds_layer_count() {
+        // Creating the DDS for an empty surface overflows when calculating the pitch.
+        let mut bntx = Bntx::from_surface(rgba8_surface(4, 4, 1, 1, 1), "tex").unwrap();
+        let brti = &mut bntx.nx_header.brtis[0].brti;
+        brti.width = 501685117;
+        brti.height = 8;
+        brti.layer_count = 0;
+        brti.mipmap_count = 4;
+        brti.tile_mode = TileMode::Linear;
+
+        let texture = bntx.texture(0).unwrap();
+        assert!(texture.to_surface().is_err());
+        assert!(matches!(
+            texture.to_dds(),
+            Err(surface::CreateDdsError::Surface(
+                surface::CreateSurfaceError::SwizzleError(
+                    tegra_swizzle::SwizzleError::InvalidSurface { .. }
+                )
+            ))
+        ));
+    }
+
+    #[test]
+    fn invalid_3d_layer_count() {
+        // Finding a mipmap shouldn't depend on the number of layers before it.
+        let surface = rgba8_surface(4, 4, 4, 1, 1);
+        let mut bntx = Bntx::from_surface(surface, "tex").unwrap();
+        bntx.nx_header.brtis[0].brti.layer_count = u32::MAX;
+
+        let texture = bntx.texture(0).unwrap();
+        assert!(texture.mip(u32::MAX - 1, 0).is_err());
+    }
+
+    #[test]
+    fn invalid_first_mipmap_offset() {
+        let mut bntx = Bntx::from_surface(rgba8_surface(4, 4, 1, 1, 1), "tex").unwrap();
+        let data_offset = bntx.nx_header.brtd.data_offset;
+        bntx.nx_header.brtis[0].brti.mipmaps.mipmap_offsets[0] = data_offset - 1;
+
+        let texture = bntx.texture(0).unwrap();
+        assert!(matches!(
+            texture.image_data(),
+            Err(surface::CreateSurfaceError::InvalidMipmapOffset { level: 0, .. })
+        ));
+        assert!(matches!(
+            texture.deswizzled_data(),
+            Err(surface::CreateSurfaceError::InvalidMipmapOffset { level: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn invalid_image_size() {
+        let mut bntx = Bntx::from_surface(rgba8_surface(4, 4, 1, 1, 1), "tex").unwrap();
+        let image_size = bntx.nx_header.brtd.image_data.len() as u32;
+        bntx.nx_header.brtis[0].brti.image_size = image_size;
+        assert!(bntx.texture(0).unwrap().image_data().is_ok());
+
+        // The image data can't extend past the end of the BRTD section.
+        bntx.nx_header.brtis[0].brti.image_size = image_size + 1;
+        let texture = bntx.texture(0).unwrap();
+        assert!(matches!(
+            texture.image_data(),
+            Err(surface::CreateSurfaceError::InvalidImageSize { .. })
+        ));
+        assert!(matches!(
+            texture.to_surface(),
+            Err(surface::CreateSurfaceError::InvalidImageSize { .. })
+        ));
+    }
+
+    #[test]
+    fn cube_array_dds_round_trip() {
+        let surface = |layers| rgba8_surface(4, 4, 1, layers, 1);
+        let options = surface::SwizzleOptions {
+            view_dimension: Some(TextureViewDimension::CubeArray),
+            ..Default::default()
+        };
+        let bntx = Bntx::from_surface_with_options(surface(12), "tex", options).unwrap();
+
+        let dds = bntx.to_dds().unwrap();
+        let header10 = dds.header10.as_ref().unwrap();
+        assert_eq!(
+            image_dds::ddsfile::MiscFlag::TEXTURECUBE,
+            header10.misc_flag
+        );
+        assert_eq!(2, header10.array_size);
+
+        let new_bntx = Bntx::from_dds(&dds, "tex").unwrap();
+        let texture = new_bntx.texture(0).unwrap();
+        assert_eq!(TextureViewDimension::CubeArray, texture.view_dimension());
+        assert_eq!(12, texture.layer_count());
+        assert_eq!(surface(12).data, texture.deswizzled_data().unwrap());
+
+        assert!(matches!(
+            Bntx::from_surface_with_options(surface(7), "tex", options),
+            Err(Error::Create {
+                source: surface::CreateBntxError::InvalidLayerCount { layer_count: 7, .. },
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn read_written_mixed_view_dimensions() {
+        let cube_array = surface::SwizzleOptions {
+            view_dimension: Some(TextureViewDimension::CubeArray),
+            ..Default::default()
+        };
+        let surfaces = [
+            ("cubes", rgba8_surface(4, 4, 1, 12, 1), cube_array),
+            (
+                "tex",
+                rgba8_surface(4, 4, 1, 1, 1),
+                surface::SwizzleOptions::default(),
+            ),
+        ];
+        let bntx = Bntx::from_surfaces_with_options(surfaces.clone(), "file").unwrap();
+
+        let bntx = round_trip(&bntx);
+
+        let textures: Vec<_> = bntx.textures().collect();
+        assert_eq!(
+            TextureViewDimension::CubeArray,
+            textures[0].view_dimension()
+        );
+        assert_eq!(12, textures[0].layer_count());
+        assert_eq!(TextureViewDimension::D2, textures[1].view_dimension());
+        for (texture, (_, surface, _)) in textures.iter().zip(surfaces) {
+            assert_eq!(surface.data, texture.deswizzled_data().unwrap());
+        }
+    }
+
+    #[test]
+    fn read_written_3d_texture() {
+        // Mipmaps of 16x16x10, 8x8x5, 4x4x2, 2x2x1, and 1x1x1 pixels.
+        let surface = rgba8_surface(16, 16, 10, 1, 5);
+        let data = surface.data.clone();
+        let bntx = Bntx::from_surface(surface, "tex").unwrap();
+
+        let bntx = round_trip(&bntx);
+
+        let texture = bntx.texture(0).unwrap();
+        assert_eq!(TextureDimension::D3, texture.brti().texture_dimension);
+        assert_eq!(TextureViewDimension::D3, texture.view_dimension());
+        assert_eq!(0, texture.brti().block_height_log2);
+        // The last mipmap is a single GOB.
+        let offsets = &texture.brti().mipmaps.mipmap_offsets;
+        assert_eq!(
+            texture.brti().image_size as u64,
+            offsets[4] - offsets[0] + 512
+        );
+        assert_eq!(data, texture.deswizzled_data_strict().unwrap());
+
+        let mip = texture.mip(0, 1).unwrap();
+        assert_eq!((8, 8, 5), (mip.width, mip.height, mip.depth));
+        assert_eq!(data[16 * 16 * 10 * 4..][..8 * 8 * 5 * 4], mip.data);
+
+        // The header block height is ignored for 3D textures unless checked.
+        let mut bntx = bntx.clone();
+        bntx.nx_header.brtis[0].brti.block_height_log2 = 2;
+        let texture = bntx.texture(0).unwrap();
+        assert_eq!(
+            tegra_swizzle::BlockHeight::Four,
+            texture.block_height().unwrap()
+        );
+        assert_eq!(data, texture.deswizzled_data().unwrap());
+        assert_eq!(mip, texture.mip(0, 1).unwrap());
+        assert!(matches!(
+            texture.deswizzled_data_strict(),
+            Err(surface::CreateSurfaceError::BlockHeightMismatch {
+                block_height: tegra_swizzle::BlockHeight::Four,
+                inferred: tegra_swizzle::BlockHeight::One,
+            })
+        ));
+
+        let dds = bntx.to_dds().unwrap();
+        assert_eq!(Some(10), dds.header.depth);
+        let new_bntx = Bntx::from_dds(&dds, "tex").unwrap();
+        assert_eq!(
+            data,
+            new_bntx.texture(0).unwrap().deswizzled_data().unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_3d_mipmap_offset() {
+        let mut bntx = Bntx::from_surface(rgba8_surface(16, 16, 10, 1, 5), "tex").unwrap();
+        let offsets = &mut bntx.nx_header.brtis[0].brti.mipmaps.mipmap_offsets;
+        let expected = offsets[2];
+        offsets[2] += 512;
+
+        let texture = bntx.texture(0).unwrap();
+        assert!(matches!(
+            texture.deswizzled_data(),
+            Err(surface::CreateSurfaceError::MipmapOffsetMismatch { level: 2, offset, expected: e })
+                if offset == expected + 512 && e == expected
+        ));
+        assert!(matches!(
+            texture.mip(0, 1),
+            Err(surface::CreateSurfaceError::MipmapOffsetMismatch { level: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn d1_array_dds_round_trip() {
+        let surface = rgba8_surface(16, 1, 1, 3, 2);
+        let data = surface.data.clone();
+        let options = surface::SwizzleOptions {
+            view_dimension: Some(TextureViewDimension::D1Array),
+            ..Default::default()
+        };
+        let bntx = Bntx::from_surface_with_options(surface, "tex", options).unwrap();
+        let texture = bntx.texture(0).unwrap();
+        assert_eq!(TextureDimension::D1, texture.brti().texture_dimension);
+        assert_eq!(data, texture.deswizzled_data().unwrap());
+
+        let dds = bntx.to_dds().unwrap();
+        let header10 = dds.header10.as_ref().unwrap();
+        assert_eq!(
+            image_dds::ddsfile::D3D10ResourceDimension::Texture1D,
+            header10.resource_dimension
+        );
+        assert_eq!(3, header10.array_size);
+
+        let new_bntx = Bntx::from_dds(&dds, "tex").unwrap();
+        let texture = new_bntx.texture(0).unwrap();
+        assert_eq!(TextureViewDimension::D1Array, texture.view_dimension());
+        assert_eq!(data, texture.deswizzled_data().unwrap());
+    }
+
+    #[test]
+    fn read_error_context() {
+        let surface = rgba8_surface(4, 4, 1, 1, 1);
+        let bntx =
+            Bntx::from_surfaces([("a", surface.clone()), ("b", surface)], "textures").unwrap();
+        let mut bytes = bntx.to_bytes().unwrap();
+
+        // Corrupt the magic for the second texture.
+        let offset = bytes
+            .windows(4)
+            .enumerate()
+            .filter(|(_, w)| w == b"BRTI")
+            .nth(1)
+            .unwrap()
+            .0;
+        bytes[offset] = b'X';
+
+        match Bntx::from_bytes(&bytes) {
+            Err(Error::Read { context, .. }) => assert_eq!(
+                ErrorContext {
+                    magic: Some(*b"BRTI"),
+                    offset: Some(offset as u64),
+                    texture_index: Some(1),
+                    texture_name: None,
+                },
+                context
+            ),
+            _ => panic!("expected a read error"),
+        }
+    }
+
+    #[test]
+    fn read_written_unknown_format() {
+        let surface = rgba8_surface(4, 4, 1, 1, 1);
+        let mut bntx = Bntx::from_surface(surface, "tex").unwrap();
+        bntx.nx_header.brtis[0].brti.image_format = SurfaceFormat::Unknown(0xff01);
+
+        let bytes = bntx.to_bytes().unwrap();
+        let bntx = Bntx::from_bytes(&bytes).unwrap();
+        let texture = bntx.texture(0).unwrap();
+        assert_eq!(SurfaceFormat::Unknown(0xff01), texture.image_format());
+        assert!(matches!(
+            texture.to_surface(),
+            Err(surface::CreateSurfaceError::UnsupportedSurfaceFormat(
+                SurfaceFormat::Unknown(0xff01)
+            ))
+        ));
+
+        assert_eq!(bytes, bntx.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn empty_file_accessors() {
+        let bntx = Bntx::from_surfaces(
+            std::iter::empty::<(&str, image_dds::Surface<Vec<u8>>)>(),
+            "empty",
+        )
+        .unwrap();
+        let bntx = round_trip(&bntx);
+        assert!(bntx.first_texture().is_none());
+        assert_eq!(None, bntx.width());
+        assert_eq!(None, bntx.image_format());
+        assert!(matches!(bntx.deswizzled_data(), Err(Error::NoTextures)));
+        assert!(matches!(bntx.to_surface(), Err(Error::NoTextures)));
+    }
+
+    #[test]
+    fn invalid_mipmap_count() {
+        let surface = rgba8_surface(4, 4, 1, 1, 1);
+        let mut bntx = Bntx::from_surface(surface, "tex").unwrap();
+        bntx.nx_header.brtis[0].brti.mipmap_count = 0xffff;
+
+        let texture = bntx.texture(0).unwrap();
+        assert!(texture.to_surface().is_err());
+        assert!(texture.mip(0, 40).is_err());
+    }
+
+    #[test]
+    fn invalid_brtd_size() {
+        let surface = rgba8_surface(4, 4, 1, 1, 1);
+        let bntx = Bntx::from_surface(surface, "tex").unwrap();
+        let mut bytes = bntx.to_bytes().unwrap();
+
+        // Set the BRTD size to less than the size of the header.
+        let offset = bytes.windows(4).position(|w| w == b"BRTD").unwrap();
+        bytes[offset + 8..offset + 16].copy_from_slice(&8u64.to_le_bytes());
+
+        match Bntx::from_bytes(&bytes) {
+            Err(Error::Read { context, .. }) => assert_eq!(Some(*b"BRTD"), context.magic),
+            _ => panic!("expected a read error"),
+        }
+    }
+
+    #[test]
+    fn read_options_max_textures() {
+        let surface = rgba8_surface(4, 4, 1, 1, 1);
+        let bntx =
+            Bntx::from_surfaces([("a", surface.clone()), ("b", surface)], "textures").unwrap();
+        let bytes = bntx.to_bytes().unwrap();
+
+        let options = ReadOptions {
+            max_textures: 1,
+            ..Default::default()
+        };
+        match Bntx::from_bytes_with_options(&bytes, options) {
+            Err(Error::Read { context, .. }) => assert_eq!(Some(*b"NX  "), context.magic),
+            _ => panic!("expected a read error"),
+        }
+        assert!(Bntx::from_bytes_with_options(&bytes, ReadOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn read_count_past_end_of_stream() {
+        let surface = rgba8_surface(4, 4, 1, 1, 1);
+        let bntx = Bntx::from_surface(surface, "tex").unwrap();
+        let mut bytes = bntx.to_bytes().unwrap();
+
+        // Set a BRTD size within the default limits but larger than the file.
+        let offset = bytes.windows(4).position(|w| w == b"BRTD").unwrap();
+        bytes[offset + 8..offset + 16].copy_from_slice(&(1u64 << 40).to_le_bytes());
+
+        match Bntx::from_bytes(&bytes) {
+            Err(Error::Read { context, .. }) => assert_eq!(Some(*b"BRTD"), context.magic),
+            _ => panic!("expected a read error"),
+        }
+    }
+
+    #[test]
+    fn read_shared_pointers() {
+        let surface = rgba8_surface(4, 4, 1, 1, 1);
+        let bntx = Bntx::from_surface(surface, "tex").unwrap();
+        let mut bytes = bntx.to_bytes().unwrap();
+
+        let u64_at = |bytes: &[u8], offset: usize| {
+            <[u8; 8]>::try_from(&bytes[offset..offset + 8])
+                .map(u64::from_le_bytes)
+                .unwrap() as usize
+        };
+        let nx = bytes.windows(4).position(|w| w == b"NX  ").unwrap();
+        let brti = u64_at(&bytes, u64_at(&bytes, nx + 8));
+
+        // Point many textures at one BRTI with the largest mipmap array.
+        bytes[brti + 0x16..brti + 0x18].copy_from_slice(&u16::MAX.to_le_bytes());
+        let table = bytes.len();
+        for _ in 0..4000 {
+            bytes.extend_from_slice(&(brti as u64).to_le_bytes());
+        }
+        bytes[nx + 4..nx + 8].copy_from_slice(&4000u32.to_le_bytes());
+        bytes[nx + 8..nx + 16].copy_from_slice(&(table as u64).to_le_bytes());
+        // Make each mipmap array fit in the stream.
+        bytes.resize(bytes.len() + u16::MAX as usize * 8, 0);
+
+        match Bntx::from_bytes(&bytes) {
+            Err(Error::Read { source, .. }) => {
+                assert!(format!("{source:?}").contains("total bytes read exceeds the limit"))
+            }
+            _ => panic!("expected a read error"),
+        }
+    }
+
+    // Find all files recursively to also support extracted game folders.
+    fn bntx_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                bntx_files(&path, files);
+            } else if path.extension().and_then(|e| e.to_str()) == Some("bntx") {
+                files.push(path);
+            }
+        }
+    }
+
+    fn fixture_files() -> Vec<std::path::PathBuf> {
+        // Set BNTX_FIXTURES to also test files that can't be included in the repository.
+        let mut dirs = vec![Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures")];
+        dirs.extend(std::env::var_os("BNTX_FIXTURES").map(Into::into));
+
+        let mut files = Vec::new();
+        for dir in dirs {
+            bntx_files(&dir, &mut files);
+        }
+        assert!(!files.is_empty());
+        files
+    }
+
+    #[test]
+    fn read_write_fixtures() {
+        let mut failures = Vec::new();
+        for path in fixture_files() {
+            let bytes = std::fs::read(&path).unwrap();
+            let result = Bntx::from_bytes(&bytes).and_then(|bntx| bntx.to_bytes());
+            match result {
+                Ok(new_bytes) if new_bytes == bytes => (),
+                Ok(new_bytes) => {
+                    let offset = bytes
+                        .iter()
+                        .zip(&new_bytes)
+                        .position(|(a, b)| a != b)
+                        .unwrap_or(bytes.len().min(new_bytes.len()));
+                    failures.push(format!(
+                        "{path:?}: first difference at 0x{offset:x} with sizes 0x{:x} and 0x{:x}",
+                        bytes.len(),
+                        new_bytes.len()
+                    ));
+                }
+                Err(e) => failures.push(format!("{path:?}: {e}")),
+            }
+        }
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    fn read_le(bytes: &[u8], offset: u64, size: usize) -> u64 {
+        let mut value = [0u8; 8];
+        value[..size].copy_from_slice(&bytes[offset as usize..offset as usize + size]);
+        u64::from_le_bytes(value)
+    }
+
+    fn magic_at(bytes: &[u8], offset: u64) -> &[u8] {
+        &bytes[offset as usize..offset as usize + 4]
+    }
+
+    fn str_at(bytes: &[u8], offset: u64) -> &[u8] {
+        let len = read_le(bytes, offset, 2) as usize;
+        &bytes[offset as usize + 2..offset as usize + 2 + len]
+    }
+
+    #[test]
+    fn fixtures_match_documented_layout() {
+        // The fixtures are written by this crate, so round trips can't catch
+        // reading and writing a field at the same wrong offset.
+        // Check the fields at the offsets from Switch Toolbox's Syroot.NintenTools.NSW.Bntx instead.
+        // Only little endian files are checked since there is no documented big endian layout.
+        let mut checked = 0;
+        for path in fixture_files() {
+            let bytes = std::fs::read(&path).unwrap();
+            if bytes[12..14] != [0xFF, 0xFE] {
+                continue;
+            }
+            let bntx = Bntx::from_bytes(&bytes).unwrap();
+
+            // Binary file header.
+            assert_eq!(b"BNTX", magic_at(&bytes, 0), "{:?}", path);
+            assert_eq!(0x40, bytes[0xF], "{:?}: address size", path);
+            let name = read_le(&bytes, 0x10, 4);
+            assert_eq!(
+                bntx.name().as_bytes(),
+                str_at(&bytes, name - 2),
+                "{:?}",
+                path
+            );
+            assert_eq!(
+                b"_STR",
+                magic_at(&bytes, read_le(&bytes, 0x16, 2)),
+                "{:?}",
+                path
+            );
+            let rlt = read_le(&bytes, 0x18, 4);
+            assert_eq!(b"_RLT", magic_at(&bytes, rlt), "{:?}", path);
+            assert_eq!(bytes.len() as u64, read_le(&bytes, 0x1C, 4), "{:?}", path);
+
+            // Texture container.
+            assert_eq!(b"NX  ", magic_at(&bytes, 0x20), "{:?}", path);
+            let count = read_le(&bytes, 0x24, 4);
+            assert_eq!(bntx.nx_header.brtis.len() as u64, count, "{:?}", path);
+            let brtd = read_le(&bytes, 0x30, 8);
+            assert_eq!(b"BRTD", magic_at(&bytes, brtd), "{:?}", path);
+            assert_eq!(
+                b"_DIC",
+                magic_at(&bytes, read_le(&bytes, 0x38, 8)),
+                "{:?}",
+                path
+            );
+            let data_start = brtd + 0x10;
+            let data_end = brtd + read_le(&bytes, brtd + 8, 8);
+            assert!(data_end <= rlt, "{:?}", path);
+
+            for i in 0..count {
+                let brti = read_le(&bytes, read_le(&bytes, 0x28, 8) + i * 8, 8);
+                assert_eq!(b"BRTI", magic_at(&bytes, brti), "{:?}", path);
+
+                let texture = bntx.texture(i as usize).unwrap();
+                let name = read_le(&bytes, brti + 0x60, 8);
+                assert_eq!(
+                    texture.name().as_bytes(),
+                    str_at(&bytes, name),
+                    "{:?}",
+                    path
+                );
+                assert_eq!(0x20, read_le(&bytes, brti + 0x68, 8), "{:?}: parent", path);
+
+                let fields = [
+                    (0x16, 2, texture.mipmap_count() as u64),
+                    (0x1C, 4, u32::from(texture.image_format()) as u64),
+                    (0x24, 4, texture.width() as u64),
+                    (0x28, 4, texture.height() as u64),
+                    (0x2C, 4, texture.depth() as u64),
+                    (0x30, 4, texture.layer_count() as u64),
+                    (0x34, 4, texture.brti().block_height_log2 as u64),
+                    (0x50, 4, texture.brti().image_size as u64),
+                    (0x54, 4, texture.brti().align as u64),
+                    (0x58, 4, u32::from(texture.brti().comp_sel) as u64),
+                    (0x5C, 4, texture.view_dimension() as u64),
+                ];
+                for (offset, size, value) in fields {
+                    assert_eq!(
+                        value,
+                        read_le(&bytes, brti + offset, size),
+                        "{:?}: texture {} field at 0x{:x}",
+                        path,
+                        i,
+                        offset
+                    );
+                }
+
+                let mipmaps = read_le(&bytes, brti + 0x70, 8);
+                let offsets = &texture.brti().mipmaps.mipmap_offsets;
+                for (m, offset) in offsets.iter().enumerate() {
+                    let value = read_le(&bytes, mipmaps + m as u64 * 8, 8);
+                    assert_eq!(*offset, value, "{:?}: texture {} mipmap {}", path, i, m);
+                    assert!((data_start..data_end).contains(offset), "{:?}", path);
+                }
+                let image_size = texture.brti().image_size as u64;
+                assert!(offsets[0] + image_size <= data_end, "{:?}", path);
+            }
+            checked += 1;
+        }
+        assert!(checked > 0);
+    }
+
+    // A surface with all mipmaps and layers filled with a repeating pattern.
+    fn fixture_surface(
+        width: u32,
+        height: u32,
+        depth: u32,
+        layers: u32,
+        mipmaps: u32,
+        image_format: image_dds::ImageFormat,
+    ) -> image_dds::Surface<Vec<u8>> {
+        let (block_size, block_width) = match image_format {
+            image_dds::ImageFormat::Rgba8Unorm => (4, 1),
+            image_dds::ImageFormat::BC1RgbaUnorm => (8, 4),
+            _ => (16, 4),
+        };
+        let layer_size: usize = (0..mipmaps)
+            .map(|m| {
+                let w = ((width >> m).max(1) as usize).div_ceil(block_width);
+                let h = ((height >> m).max(1) as usize).div_ceil(block_width);
+                w * h * (depth >> m).max(1) as usize * block_size
+            })
+            .sum();
+        image_dds::Surface {
+            width,
+            height,
+            depth,
+            layers,
+            mipmaps,
+            image_format,
+            data: (0..layer_size * layers as usize)
+                .map(|i| (i * 7 + i / 251) as u8)
+                .collect(),
+        }
+    }
+
+    // Regenerate the files in fixtures with the following command.
+    // cargo test generate_fixtures -- --ignored
+    // The files are written by this crate, so they only test that parsed files are preserved.
+    #[test]
+    #[ignore]
+    fn generate_fixtures() {
+        use image_dds::ImageFormat;
+
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+
+        let single = Bntx::from_surface(
+            fixture_surface(4, 4, 1, 1, 1, ImageFormat::Rgba8Unorm),
+            "tex",
+        )
+        .unwrap();
+        single.save(dir.join("single.bntx")).unwrap();
+
+        let multiple = Bntx::from_surfaces(
+            [
+                (
+                    "chr_col",
+                    fixture_surface(64, 64, 1, 1, 7, ImageFormat::BC7RgbaUnorm),
+                ),
+                (
+                    "chr_cube",
+                    fixture_surface(16, 16, 1, 6, 5, ImageFormat::BC1RgbaUnorm),
+                ),
+                (
+                    "chr_vol",
+                    fixture_surface(8, 8, 4, 1, 3, ImageFormat::Rgba8Unorm),
+                ),
+            ],
+            "chr",
+        )
+        .unwrap();
+        multiple.save(dir.join("multiple.bntx")).unwrap();
+
+        let mut linear = Bntx::from_surfaces_with_options(
+            [(
+                "arr",
+                fixture_surface(8, 8, 1, 2, 4, ImageFormat::Rgba8Unorm),
+                surface::SwizzleOptions {
+                    tile_mode: TileMode::Linear,
+                    ..Default::default()
+                },
+            )],
+            "linear",
+        )
+        .unwrap();
+        linear.bom = ByteOrder::BigEndian;
+        linear.save(dir.join("linear_big_endian.bntx")).unwrap();
+
+        // Use values that differ from the defaults for new files.
+        let mut layout = multiple.clone();
+        let delta = layout.nx_header.brtd.data_offset - 0xc00;
+        layout.nx_header.brtd.data_offset -= delta;
+        for brti in &mut layout.nx_header.brtis {
+            for offset in &mut brti.brti.mipmaps.mipmap_offsets {
+                *offset -= delta;
+            }
+            brti.brti.unk = [1, 2, 3, 4];
+        }
+        layout.nx_header.brtd.unk = 0xABCD;
+        layout.nx_header.unk[41] = 0x1122334455667788;
+        layout.nx_header.brtis[1].brti.unk6[0] = 5;
+        layout.nx_header.brtis[2].brti.unk7[255] = 9;
+
+        // Split the entry for the first two BRTI pointers into one entry per BRTI.
+        let table = &mut round_trip(&layout).header.reloc_table;
+        let entry = table.entries[1].clone();
+        assert_eq!(2, entry.struct_count);
+        let stride = (entry.offset_count + entry.padding_count) as u32 * 8;
+        table.entries.splice(
+            1..2,
+            [
+                RelocationEntry {
+                    struct_count: 1,
+                    ..entry.clone()
+                },
+                RelocationEntry {
+                    position: entry.position + stride,
+                    struct_count: 1,
+                    offset_count: entry.offset_count,
+                    padding_count: 0,
+                },
+            ],
+        );
+        table.sections[0].count += 1;
+        table.sections[1].entry_start_index += 1;
+        layout.header.reloc_table = table.clone();
+        layout.save(dir.join("layout.bntx")).unwrap();
+    }
 }
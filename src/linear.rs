@@ -0,0 +1,171 @@
+//! Pitch linear surfaces with each row of blocks padded to [PITCH_ALIGNMENT] bytes.
+//!
+//! Mipmaps are stored sequentially for each layer with no additional padding.
+use tegra_swizzle::{div_round_up, surface::BlockDim, SwizzleError};
+
+/// The alignment in bytes for the start of each row of blocks.
+pub(crate) const PITCH_ALIGNMENT: usize = 32;
+
+/// The size in bytes of a row of blocks for a linear mipmap including padding.
+pub(crate) fn pitch(width: u32, block_dim: BlockDim, bytes_per_pixel: u32) -> usize {
+    row_size(width, block_dim, bytes_per_pixel).next_multiple_of(PITCH_ALIGNMENT)
+}
+
+/// The size in bytes of each linear mipmap including padding.
+pub(crate) fn mip_sizes(
+    width: u32,
+    height: u32,
+    depth: u32,
+    block_dim: BlockDim,
+    bytes_per_pixel: u32,
+    mipmap_count: u32,
+) -> impl Iterator<Item = usize> {
+    (0..mipmap_count).map(move |mip| {
+        let (mip_width, rows) = mip_dimensions(width, height, depth, block_dim, mip);
+        pitch(mip_width, block_dim, bytes_per_pixel) * rows
+    })
+}
+
+/// Add row padding to tightly packed data for all layers and mipmaps.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn swizzle(
+    width: u32,
+    height: u32,
+    depth: u32,
+    source: &[u8],
+    block_dim: BlockDim,
+    bytes_per_pixel: u32,
+    mipmap_count: u32,
+    layer_count: u32,
+) -> Result<Vec<u8>, SwizzleError> {
+    let rows = rows(
+        width,
+        height,
+        depth,
+        block_dim,
+        bytes_per_pixel,
+        mipmap_count,
+    )?;
+
+    let layer_size: usize = rows
+        .iter()
+        .map(|(row_size, _, count)| row_size * count)
+        .sum();
+    check_size(source, layer_size, layer_count)?;
+
+    let mut result = Vec::new();
+    let mut offset = 0;
+    for _ in 0..layer_count {
+        for (row_size, pitch, count) in &rows {
+            for _ in 0..*count {
+                result.extend_from_slice(&source[offset..offset + row_size]);
+                result.resize(result.len() + pitch - row_size, 0);
+                offset += row_size;
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Remove row padding from linear data for all layers and mipmaps.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn deswizzle(
+    width: u32,
+    height: u32,
+    depth: u32,
+    source: &[u8],
+    block_dim: BlockDim,
+    bytes_per_pixel: u32,
+    mipmap_count: u32,
+    layer_count: u32,
+) -> Result<Vec<u8>, SwizzleError> {
+    let rows = rows(
+        width,
+        height,
+        depth,
+        block_dim,
+        bytes_per_pixel,
+        mipmap_count,
+    )?;
+
+    let layer_size: usize = rows.iter().map(|(_, pitch, count)| pitch * count).sum();
+    check_size(source, layer_size, layer_count)?;
+
+    let mut result = Vec::new();
+    let mut offset = 0;
+    for _ in 0..layer_count {
+        for (row_size, pitch, count) in &rows {
+            for _ in 0..*count {
+                result.extend_from_slice(&source[offset..offset + row_size]);
+                offset += pitch;
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn row_size(width: u32, block_dim: BlockDim, bytes_per_pixel: u32) -> usize {
+    div_round_up(width, block_dim.width.get()) as usize * bytes_per_pixel as usize
+}
+
+// The width in pixels and the number of rows of blocks for a mipmap.
+fn mip_dimensions(
+    width: u32,
+    height: u32,
+    depth: u32,
+    block_dim: BlockDim,
+    mip: u32,
+) -> (u32, usize) {
+    let mip_width = (width >> mip).max(1);
+    let mip_height = div_round_up((height >> mip).max(1), block_dim.height.get());
+    let mip_depth = div_round_up((depth >> mip).max(1), block_dim.depth.get());
+    (mip_width, mip_height as usize * mip_depth as usize)
+}
+
+// The unpadded size, padded size, and number of rows for each mipmap in a layer.
+fn rows(
+    width: u32,
+    height: u32,
+    depth: u32,
+    block_dim: BlockDim,
+    bytes_per_pixel: u32,
+    mipmap_count: u32,
+) -> Result<Vec<(usize, usize, usize)>, SwizzleError> {
+    // Check dimensions to prevent overflow like tegra_swizzle.
+    if width
+        .checked_mul(height)
+        .and_then(|u| u.checked_mul(depth))
+        .and_then(|u| u.checked_mul(bytes_per_pixel))
+        .is_none()
+        || mipmap_count > u32::BITS
+    {
+        return Err(SwizzleError::InvalidSurface {
+            width,
+            height,
+            depth,
+            bytes_per_pixel,
+            mipmap_count,
+        });
+    }
+
+    Ok((0..mipmap_count)
+        .map(|mip| {
+            let (mip_width, count) = mip_dimensions(width, height, depth, block_dim, mip);
+            (
+                row_size(mip_width, block_dim, bytes_per_pixel),
+                pitch(mip_width, block_dim, bytes_per_pixel),
+                count,
+            )
+        })
+        .collect())
+}
+
+fn check_size(source: &[u8], layer_size: usize, layer_count: u32) -> Result<(), SwizzleError> {
+    match layer_size.checked_mul(layer_count as usize) {
+        Some(expected_size) if source.len() >= expected_size => Ok(()),
+        expected_size => Err(SwizzleError::NotEnoughData {
+            expected_size: expected_size.unwrap_or(usize::MAX),
+            actual_size: source.len(),
+        }),
+    }
+}
@@ -1,6 +1,9 @@
 use std::convert::{TryFrom, TryInto};
 
-use image_dds::{ddsfile::Dds, Surface};
+use image_dds::{
+    ddsfile::{Caps2, D3D10ResourceDimension, Dds, MiscFlag},
+    Surface, SurfaceRgba8,
+};
 use tegra_swizzle::{
     block_height_mip0, div_round_up, mip_block_height,
     surface::{swizzle_surface, BlockDim},
@@ -9,9 +12,9 @@ use tegra_swizzle::{
 use thiserror::Error;
 
 use crate::{
-    Bntx, BntxStr, Brtd, Brti, BrtiOffset, ByteOrder, DictNode, DictSection, Header, Mipmaps,
-    NxHeader, RelocationEntry, RelocationSection, RelocationTable, StrSection, SurfaceFormat,
-    TextureDimension, TextureViewDimension,
+    astc, linear, Bntx, BntxStr, Brtd, Brti, BrtiOffset, ByteOrder, ChannelSelector, DictSection,
+    Error, ErrorContext, Header, Mipmaps, NxHeader, RelocationTable, StrSection, SurfaceFormat,
+    Texture, TextureViewDimension, TileMode,
 };
 
 #[derive(Debug, Error)]
@@ -24,6 +27,43 @@ pub enum CreateBntxError {
 
     #[error("unsupported format {0:?}")]
     UnsupportedImageFormat(image_dds::ImageFormat),
+
+    #[error("unsupported tile mode {0:?}")]
+    UnsupportedTileMode(TileMode),
+
+    #[error("{layer_count} layers is not valid for view dimension {view_dimension:?}")]
+    InvalidLayerCount {
+        view_dimension: TextureViewDimension,
+        layer_count: u32,
+    },
+
+    #[error(
+        "dimensions {width}x{height}x{depth} are not valid for view dimension {view_dimension:?}"
+    )]
+    InvalidDimensions {
+        view_dimension: TextureViewDimension,
+        width: u32,
+        height: u32,
+        depth: u32,
+    },
+
+    #[error("error creating texture dictionary")]
+    Dict(#[from] CreateDictError),
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum CreateDictError {
+    #[error("texture names must not be empty")]
+    EmptyName,
+
+    #[error("texture name {0:?} is used more than once")]
+    DuplicateName(String),
+
+    #[error("texture names {0:?} and {1:?} have no differing bits")]
+    IndistinguishableNames(String, String),
+
+    #[error("{0} texture names exceeds the maximum of {max}", max = u16::MAX)]
+    TooManyNames(usize),
 }
 
 #[derive(Debug, Error)]
@@ -36,6 +76,14 @@ pub enum CreateDdsError {
 
     #[error("error creating DDS")]
     Dds(#[from] image_dds::CreateDdsError),
+
+    #[error("dimensions {width}x{height}x{depth} with {layers} layers are too large for DDS")]
+    SurfaceTooLarge {
+        width: u32,
+        height: u32,
+        depth: u32,
+        layers: u32,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -45,13 +93,83 @@ pub enum CreateSurfaceError {
 
     #[error("unsupported format {0:?}")]
     UnsupportedSurfaceFormat(SurfaceFormat),
+
+    #[error("error decoding surface")]
+    Decode(#[from] image_dds::error::SurfaceError),
+
+    #[error("unsupported tile mode {0:?}")]
+    UnsupportedTileMode(TileMode),
+
+    #[error("layer {layer} mipmap {level} is out of range")]
+    MipOutOfRange { layer: u32, level: u32 },
+
+    #[error("missing offset for mipmap {0}")]
+    MissingMipmapOffset(u32),
+
+    #[error("offset {offset} for mipmap {level} is outside the image data")]
+    InvalidMipmapOffset { level: u32, offset: u64 },
+
+    #[error("image size {image_size} at offset {offset} extends past the end of the image data")]
+    InvalidImageSize { offset: u64, image_size: u32 },
+
+    #[error("unknown channel source in selector {0:?}")]
+    UnknownChannelSelector(ChannelSelector),
+
+    #[error("offset {offset} for mipmap {level} does not match the expected offset {expected}")]
+    MipmapOffsetMismatch {
+        level: u32,
+        offset: u64,
+        expected: u64,
+    },
+
+    #[error("invalid block height log2 {0}")]
+    InvalidBlockHeight(u32),
+
+    #[error("block height {block_height:?} does not match the inferred block height {inferred:?}")]
+    BlockHeightMismatch {
+        block_height: BlockHeight,
+        inferred: BlockHeight,
+    },
+}
+
+/// Options for swizzling new textures.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SwizzleOptions {
+    /// The block height for the first mipmap or `None` to infer it from the height and format.
+    /// Ignored for [TileMode::Linear] and 3D textures.
+    pub block_height: Option<BlockHeight>,
+    /// The memory layout for the image data.
+    pub tile_mode: TileMode,
+    /// The view dimension or `None` to infer it from the depth and layer count.
+    pub view_dimension: Option<TextureViewDimension>,
+}
+
+/// Options for converting textures to surfaces and DDS files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SurfaceOptions {
+    /// Remap the pixels using [Brti::comp_sel] to match how the texture is sampled in game.
+    /// The surface is decoded to [image_dds::ImageFormat::Rgba8Unorm]
+    /// or [image_dds::ImageFormat::Rgba8UnormSrgb] to apply the selector.
+    pub apply_channel_selector: bool,
 }
 
 // Filled in during writing by xc3_write.
 const TEMP_OFFSET: u32 = 0;
 
-impl Bntx {
+impl Texture<'_> {
+    /// Deswizzle the image data to a surface.
+    ///
+    /// ASTC formats are decoded to [image_dds::ImageFormat::Rgba8Unorm]
+    /// or [image_dds::ImageFormat::Rgba8UnormSrgb].
+    /// ASTC textures with HDR blocks use [image_dds::ImageFormat::Rgba16Float] instead.
+    ///
+    /// The channel selector [Brti::comp_sel] is not applied.
+    /// See [Texture::to_surface_with_options] for also applying the selector.
     pub fn to_surface(&self) -> Result<Surface<Vec<u8>>, CreateSurfaceError> {
+        if self.image_format().is_astc() {
+            return self.decode_astc();
+        }
+
         Ok(Surface {
             width: self.width(),
             height: self.height(),
@@ -63,69 +181,266 @@ impl Bntx {
         })
     }
 
+    /// Deswizzle the image data to a surface using the given `options`.
+    pub fn to_surface_with_options(
+        &self,
+        options: SurfaceOptions,
+    ) -> Result<Surface<Vec<u8>>, CreateSurfaceError> {
+        if !options.apply_channel_selector {
+            return self.to_surface();
+        }
+
+        let surface = self.to_surface_rgba8(true)?;
+        Ok(Surface {
+            width: surface.width,
+            height: surface.height,
+            depth: surface.depth,
+            layers: surface.layers,
+            mipmaps: surface.mipmaps,
+            image_format: if self.image_format().is_srgb() {
+                image_dds::ImageFormat::Rgba8UnormSrgb
+            } else {
+                image_dds::ImageFormat::Rgba8Unorm
+            },
+            data: surface.data,
+        })
+    }
+
+    /// Convert the texture to a DDS file.
+    ///
+    /// The channel selector [Brti::comp_sel] is not applied
+    /// since DDS files have no equivalent and remapping requires decoding compressed formats.
+    /// See [Texture::to_dds_with_options] for also applying the selector.
     pub fn to_dds(&self) -> Result<Dds, CreateDdsError> {
-        image_dds::Surface {
-            height: self.height(),
+        self.to_dds_with_options(SurfaceOptions::default())
+    }
+
+    /// Convert the texture to a DDS file using the given `options`.
+    pub fn to_dds_with_options(&self, options: SurfaceOptions) -> Result<Dds, CreateDdsError> {
+        let surface = self.to_surface_with_options(options)?;
+        validate_dds_size(&surface)?;
+        let mut dds = surface.to_dds()?;
+        set_dds_view_dimension(&mut dds, self.view_dimension(), self.layer_count());
+        Ok(dds)
+    }
+
+    /// Decode all layers and mipmaps to RGBA8.
+    ///
+    /// If `apply_channel_selector` is `true`, the pixels are remapped using [Brti::comp_sel]
+    /// to match how the texture is sampled in game.
+    pub fn to_surface_rgba8(
+        &self,
+        apply_channel_selector: bool,
+    ) -> Result<SurfaceRgba8<Vec<u8>>, CreateSurfaceError> {
+        let mut surface = self.to_surface()?.decode_rgba8()?;
+        if apply_channel_selector {
+            let selector = self.brti().comp_sel;
+            if !selector.is_known() {
+                return Err(CreateSurfaceError::UnknownChannelSelector(selector));
+            }
+            for pixel in surface.data.chunks_exact_mut(4) {
+                let selected = selector.apply([pixel[0], pixel[1], pixel[2], pixel[3]]);
+                pixel.copy_from_slice(&selected);
+            }
+        }
+        Ok(surface)
+    }
+
+    fn decode_astc(&self) -> Result<Surface<Vec<u8>>, CreateSurfaceError> {
+        let format = self.image_format();
+        let block_dim = format
+            .block_dim()
+            .ok_or(CreateSurfaceError::UnsupportedSurfaceFormat(format))?;
+        let (block_width, block_height) = (block_dim.width.get(), block_dim.height.get());
+        let srgb = format.is_srgb();
+
+        let data = self.deswizzled_data()?;
+        let hdr = !srgb && astc::is_hdr(&data, block_width, block_height);
+
+        // The deswizzled data has all mipmaps for each layer.
+        let mut decoded = Vec::new();
+        let mut offset = 0;
+        for _ in 0..self.layer_count() {
+            for mip in 0..self.mipmap_count() {
+                let width = (self.width() >> mip).max(1);
+                let height = (self.height() >> mip).max(1);
+                let depth = (self.depth() >> mip).max(1);
+                let size = div_round_up(width, block_width) as usize
+                    * div_round_up(height, block_height) as usize
+                    * depth as usize
+                    * 16;
+                let mip_data = data.get(offset..offset + size).ok_or(
+                    tegra_swizzle::SwizzleError::NotEnoughData {
+                        expected_size: offset + size,
+                        actual_size: data.len(),
+                    },
+                )?;
+                decoded.extend(astc::decode(
+                    mip_data,
+                    width,
+                    height,
+                    depth,
+                    block_width,
+                    block_height,
+                    srgb,
+                    hdr,
+                ));
+                offset += size;
+            }
+        }
+
+        Ok(Surface {
             width: self.width(),
+            height: self.height(),
             depth: self.depth(),
             layers: self.layer_count(),
             mipmaps: self.mipmap_count(),
-            image_format: self.image_format().try_into()?,
-            data: self.deswizzled_data()?,
-        }
-        .to_dds()
-        .map_err(Into::into)
+            image_format: if hdr {
+                image_dds::ImageFormat::Rgba16Float
+            } else if srgb {
+                image_dds::ImageFormat::Rgba8UnormSrgb
+            } else {
+                image_dds::ImageFormat::Rgba8Unorm
+            },
+            data: decoded,
+        })
     }
+}
 
-    pub fn from_surface<T: AsRef<[u8]>>(
-        surface: Surface<T>,
-        name: &str,
-    ) -> Result<Self, CreateBntxError> {
-        // Let tegra_swizzle calculate the block height.
-        // This matches the value inferred for missing block heights like in nutexb.
-        let format = SurfaceFormat::try_from(surface.image_format)?;
-        let block_dim = format.block_dim();
-        let block_height = block_height_mip0(div_round_up(surface.height, block_dim.height.get()));
-        let block_height_log2 = (block_height as u32).ilog2();
-        let bytes_per_pixel = format.bytes_per_pixel();
-        let width = surface.width;
-        let height = surface.height;
-        let depth = surface.depth;
-        let mipmap_count = surface.mipmaps;
-        let layer_count = surface.layers;
-
-        let data = swizzle_surface(
+// ddsfile calculates the pitch and data size with unchecked u32 math.
+// Use an upper bound of 128 bits per pixel to not depend on the format.
+fn validate_dds_size(surface: &Surface<Vec<u8>>) -> Result<(), CreateDdsError> {
+    let Surface {
+        width,
+        height,
+        depth,
+        layers,
+        mipmaps,
+        ..
+    } = *surface;
+    let pitch = u64::from(width) * 128;
+    // Each mipmap is at most the size of the first mipmap.
+    let data_size = [height, depth, mipmaps.max(1), layers]
+        .iter()
+        .try_fold(u64::from(width) * 16, |size, &n| {
+            size.checked_mul(u64::from(n))
+        });
+    match data_size {
+        Some(data_size)
+            if layers > 0 && pitch <= u32::MAX as u64 && data_size <= u32::MAX as u64 =>
+        {
+            Ok(())
+        }
+        _ => Err(CreateDdsError::SurfaceTooLarge {
             width,
             height,
             depth,
-            surface.data.as_ref(),
-            block_dim,
-            Some(block_height),
-            bytes_per_pixel,
-            mipmap_count,
-            layer_count,
-        )?;
+            layers,
+        }),
+    }
+}
+
+impl Bntx {
+    /// Convert the first texture to a surface.
+    pub fn to_surface(&self) -> Result<Surface<Vec<u8>>, Error> {
+        let texture = self.first_texture().ok_or(Error::NoTextures)?;
+        texture.to_surface().map_err(|source| Error::Surface {
+            context: ErrorContext::texture(0, texture.name()),
+            source,
+        })
+    }
+
+    /// Convert the first texture to a DDS file.
+    pub fn to_dds(&self) -> Result<Dds, Error> {
+        let texture = self.first_texture().ok_or(Error::NoTextures)?;
+        texture.to_dds().map_err(|source| Error::Dds {
+            context: ErrorContext::texture(0, texture.name()),
+            source,
+        })
+    }
+
+    /// Create a file with a single texture.
+    /// The texture `name` is also used as the file name.
+    pub fn from_surface<T: AsRef<[u8]>>(surface: Surface<T>, name: &str) -> Result<Self, Error> {
+        Self::from_surfaces([(name, surface)], name)
+    }
+
+    /// Create a file with a single texture using the given swizzle `options`.
+    /// The texture `name` is also used as the file name.
+    pub fn from_surface_with_options<T: AsRef<[u8]>>(
+        surface: Surface<T>,
+        name: &str,
+        options: SwizzleOptions,
+    ) -> Result<Self, Error> {
+        Self::from_surfaces_with_options([(name, surface, options)], name)
+    }
+
+    /// Create a file with a texture for each named surface in order.
+    pub fn from_surfaces<'a, T: AsRef<[u8]>>(
+        surfaces: impl IntoIterator<Item = (&'a str, Surface<T>)>,
+        name: &str,
+    ) -> Result<Self, Error> {
+        Self::from_surfaces_with_options(
+            surfaces
+                .into_iter()
+                .map(|(name, surface)| (name, surface, SwizzleOptions::default())),
+            name,
+        )
+    }
+
+    /// Create a file with a texture for each named surface in order
+    /// using the swizzle options for each texture.
+    pub fn from_surfaces_with_options<'a, T: AsRef<[u8]>>(
+        surfaces: impl IntoIterator<Item = (&'a str, Surface<T>, SwizzleOptions)>,
+        name: &str,
+    ) -> Result<Self, Error> {
+        // The file name is the first string.
+        let mut strings = vec![BntxStr {
+            chars: name.to_string(),
+        }];
+
+        let mut brtis = Vec::new();
+        let mut image_data = Vec::new();
+        for (i, (name, surface, options)) in surfaces.into_iter().enumerate() {
+            // Empty names can't be found in the dictionary.
+            if name.is_empty() {
+                return Err(Error::Create {
+                    context: ErrorContext::texture(i, name),
+                    source: CreateDictError::EmptyName.into(),
+                });
+            }
+
+            // Each texture starts at an aligned offset in the BRTD section.
+            let (brti, data) =
+                create_brti(surface, name, image_data.len(), options).map_err(|source| {
+                    Error::Create {
+                        context: ErrorContext::texture(i, name),
+                        source,
+                    }
+                })?;
+            image_data.resize(image_data.len().next_multiple_of(brti.align as usize), 0u8);
+            image_data.extend_from_slice(&data);
+
+            if !strings.iter().any(|s| s.chars == name) {
+                strings.push(BntxStr {
+                    chars: name.to_string(),
+                });
+            }
+
+            brtis.push(BrtiOffset { brti });
+        }
+
+        let names: Vec<_> = brtis.iter().map(|b| b.brti.name.as_str()).collect();
+        let dict = create_dict(&names)?;
 
         let str_section = StrSection {
             block_size: TEMP_OFFSET,
             block_offset: TEMP_OFFSET as u64,
-            str_count: 1,
+            str_count: strings.len() as u32,
             empty: BntxStr::default(),
-            strings: vec![BntxStr {
-                chars: name.to_string(),
-            }],
+            strings,
         };
 
-        let mipmap_offsets = calculate_mipmap_offsets(
-            mipmap_count,
-            width,
-            block_dim,
-            height,
-            depth,
-            block_height,
-            bytes_per_pixel,
-        );
-
         Ok(Self {
             unk: 0,
             version: (0, 4),
@@ -133,147 +448,255 @@ impl Bntx {
             header: Header {
                 revision: 0x400c,
                 file_name: TEMP_OFFSET,
+                name: name.to_string(),
                 unk: 0,
                 str_section,
-                // TODO: how to initialize this data?
-                // TODO: switch toolbox generates more entries to avoid setting padding count?
+                // The relocation table is generated when writing.
                 reloc_table: RelocationTable {
-                    position: TEMP_OFFSET,
-                    count: 2,
+                    position: 0,
+                    count: 0,
                     unk1: 0,
-                    sections: vec![
-                        // Data until end of BRTIs
-                        RelocationSection {
-                            pointer: 0,
-                            position: TEMP_OFFSET,
-                            size: TEMP_OFFSET,
-                            entry_start_index: 0,
-                            count: 4,
-                        },
-                        // BRTD to _RLT
-                        RelocationSection {
-                            pointer: 0,
-                            position: TEMP_OFFSET,
-                            size: TEMP_OFFSET,
-                            entry_start_index: 4,
-                            count: 1,
-                        },
-                    ],
-                    entries: vec![
-                        // Section 0
-                        RelocationEntry {
-                            position: TEMP_OFFSET,
-                            struct_count: 2,
-                            offset_count: 1,
-                            padding_count: 45,
-                        },
-                        RelocationEntry {
-                            position: TEMP_OFFSET,
-                            struct_count: 2,
-                            offset_count: 2,
-                            padding_count: 0,
-                        },
-                        RelocationEntry {
-                            position: TEMP_OFFSET,
-                            struct_count: 2,
-                            offset_count: 1,
-                            padding_count: 1,
-                        },
-                        RelocationEntry {
-                            position: TEMP_OFFSET,
-                            struct_count: 1,
-                            offset_count: 3,
-                            padding_count: 0,
-                        },
-                        // Section 1
-                        RelocationEntry {
-                            position: TEMP_OFFSET,
-                            struct_count: 2,
-                            offset_count: 1,
-                            padding_count: 0,
-                        },
-                    ],
+                    sections: Vec::new(),
+                    entries: Vec::new(),
                 },
                 file_size: TEMP_OFFSET,
             },
             nx_header: NxHeader {
-                brtis: vec![BrtiOffset {
-                    brti: Brti {
-                        size: TEMP_OFFSET,
-                        size2: TEMP_OFFSET as u64,
-                        flags: 1,
-                        texture_dimension: if depth > 1 {
-                            TextureDimension::D3
-                        } else {
-                            TextureDimension::D2
-                        },
-                        tile_mode: 0,
-                        swizzle: 0,
-                        mipmap_count: mipmap_count as u16,
-                        multi_sample_count: 1,
-                        image_format: format,
-                        unk2: 32,
-                        width,
-                        height,
-                        depth,
-                        layer_count,
-                        block_height_log2,
-                        unk4: [65543, 0, 0, 0, 0, 0],
-                        image_size: data.len() as u32,
-                        align: 512,
-                        comp_sel: 84148994,
-                        texture_view_dimension: if depth > 1 {
-                            TextureViewDimension::D3
-                        } else if layer_count == 6 {
-                            TextureViewDimension::Cube
-                        } else {
-                            TextureViewDimension::D2
-                        },
-                        name_addr: TEMP_OFFSET as u64,
-                        parent_addr: 32,
-                        mipmaps: Mipmaps { mipmap_offsets },
-                        unk5: 0,
-                        unk6: [0; 256],
-                        unk7: [0; 256],
-                        unk: [0; 4],
-                    },
-                }],
-                brtd: Brtd { image_data: data },
-                dict: DictSection {
-                    node_count: 1,
-                    nodes: vec![
-                        DictNode {
-                            reference: -1,
-                            left_index: 1,
-                            right_index: 0,
-                            name_offset: 436,
-                        },
-                        DictNode {
-                            reference: 0, // TODO: 0 or 1?
-                            left_index: 0,
-                            right_index: 1,
-                            name_offset: 440,
-                        },
-                    ],
+                brtis,
+                brtd: Brtd {
+                    unk: 0,
+                    data_offset: START_OF_TEXTURE_DATA,
+                    image_data,
                 },
+                dict,
                 dict_size: 88,
                 unk: [0; 42],
             },
         })
     }
 
-    pub fn from_dds(dds: &Dds, name: &str) -> Result<Self, CreateBntxError> {
-        let surface = image_dds::Surface::from_dds(dds)?;
-        Self::from_surface(surface, name)
+    /// Create a file with a single texture.
+    /// DDS cube maps and 1D textures use the corresponding [TextureViewDimension].
+    pub fn from_dds(dds: &Dds, name: &str) -> Result<Self, Error> {
+        let surface = image_dds::Surface::from_dds(dds).map_err(|e| Error::Create {
+            context: ErrorContext::texture(0, name),
+            source: e.into(),
+        })?;
+        let cube = dds.header.caps2.contains(Caps2::CUBEMAP);
+        let d1 = dds.header10.as_ref().map(|h| h.resource_dimension)
+            == Some(D3D10ResourceDimension::Texture1D);
+        let view_dimension = match (cube, d1, surface.layers) {
+            (true, _, 6) => Some(TextureViewDimension::Cube),
+            (true, _, _) => Some(TextureViewDimension::CubeArray),
+            (false, true, 1) => Some(TextureViewDimension::D1),
+            (false, true, _) => Some(TextureViewDimension::D1Array),
+            (false, false, 6) => Some(TextureViewDimension::D2Array),
+            (false, false, _) => None,
+        };
+        let options = SwizzleOptions {
+            view_dimension,
+            ..Default::default()
+        };
+        Self::from_surface_with_options(surface, name, options)
+    }
+}
+
+// image_dds only creates cube maps for surfaces with exactly 6 layers
+// and does not create 1D textures.
+fn set_dds_view_dimension(dds: &mut Dds, view_dimension: TextureViewDimension, layer_count: u32) {
+    let cube = view_dimension.is_cube();
+    if let Some(header10) = &mut dds.header10 {
+        header10.misc_flag.set(MiscFlag::TEXTURECUBE, cube);
+        header10.array_size = if cube { layer_count / 6 } else { layer_count };
+        if view_dimension.is_1d() {
+            header10.resource_dimension = D3D10ResourceDimension::Texture1D;
+        }
+    }
+    dds.header
+        .caps2
+        .set(Caps2::CUBEMAP | Caps2::CUBEMAP_ALLFACES, cube);
+}
+
+fn infer_view_dimension(depth: u32, layer_count: u32) -> TextureViewDimension {
+    if depth > 1 {
+        TextureViewDimension::D3
+    } else if layer_count == 6 {
+        TextureViewDimension::Cube
+    } else if layer_count > 1 {
+        TextureViewDimension::D2Array
+    } else {
+        TextureViewDimension::D2
+    }
+}
+
+/// Build the dictionary with the texture name as context for errors.
+pub(crate) fn create_dict(names: &[&str]) -> Result<DictSection, Error> {
+    DictSection::from_names(names).map_err(|source| {
+        let name = match &source {
+            CreateDictError::DuplicateName(name)
+            | CreateDictError::IndistinguishableNames(name, _) => Some(name.clone()),
+            CreateDictError::EmptyName | CreateDictError::TooManyNames(_) => None,
+        };
+        Error::Create {
+            context: ErrorContext {
+                texture_name: name,
+                ..Default::default()
+            },
+            source: source.into(),
+        }
+    })
+}
+
+/// Swizzle the surface and create the texture info for data starting at `data_offset` in the BRTD section.
+fn create_brti<T: AsRef<[u8]>>(
+    surface: Surface<T>,
+    name: &str,
+    data_offset: usize,
+    options: SwizzleOptions,
+) -> Result<(Brti, Vec<u8>), CreateBntxError> {
+    let format = SurfaceFormat::try_from(surface.image_format)?;
+    let unsupported = || CreateBntxError::UnsupportedImageFormat(surface.image_format);
+    let block_dim = format.block_dim().ok_or_else(unsupported)?;
+    // Let tegra_swizzle calculate the block height if not specified.
+    // This matches the value inferred for missing block heights like in nutexb.
+    // 3D textures always use a block height of one and a block depth based on the depth.
+    let block_height = if surface.depth > 1 {
+        BlockHeight::One
+    } else {
+        options.block_height.unwrap_or_else(|| {
+            block_height_mip0(div_round_up(surface.height, block_dim.height.get()))
+        })
+    };
+    let block_height_log2 = (block_height as u32).ilog2();
+    let bytes_per_pixel = format.bytes_per_pixel().ok_or_else(unsupported)?;
+    let width = surface.width;
+    let height = surface.height;
+    let depth = surface.depth;
+    let mipmap_count = surface.mipmaps;
+    let layer_count = surface.layers;
+
+    let view_dimension = options
+        .view_dimension
+        .unwrap_or_else(|| infer_view_dimension(depth, layer_count));
+    let valid_layer_count = match view_dimension {
+        TextureViewDimension::Cube => layer_count == 6,
+        TextureViewDimension::CubeArray => layer_count > 0 && layer_count.is_multiple_of(6),
+        _ => true,
+    };
+    if !valid_layer_count {
+        return Err(CreateBntxError::InvalidLayerCount {
+            view_dimension,
+            layer_count,
+        });
+    }
+    if view_dimension.is_1d() && (height != 1 || depth != 1) {
+        return Err(CreateBntxError::InvalidDimensions {
+            view_dimension,
+            width,
+            height,
+            depth,
+        });
     }
+
+    let align = 512;
+    let start_offset = START_OF_TEXTURE_DATA + data_offset.next_multiple_of(align) as u64;
+    let (data, mipmap_offsets, block_height_log2) = match options.tile_mode {
+        TileMode::BlockLinear => {
+            let data = swizzle_surface(
+                width,
+                height,
+                depth,
+                surface.data.as_ref(),
+                block_dim,
+                Some(block_height),
+                bytes_per_pixel,
+                mipmap_count,
+                layer_count,
+            )?;
+            let mipmap_offsets = calculate_mipmap_offsets(
+                start_offset,
+                mipmap_count,
+                width,
+                block_dim,
+                height,
+                depth,
+                block_height,
+                bytes_per_pixel,
+            );
+            (data, mipmap_offsets, block_height_log2)
+        }
+        TileMode::Linear => {
+            let data = linear::swizzle(
+                width,
+                height,
+                depth,
+                surface.data.as_ref(),
+                block_dim,
+                bytes_per_pixel,
+                mipmap_count,
+                layer_count,
+            )?;
+            let mipmap_offsets = linear::mip_sizes(
+                width,
+                height,
+                depth,
+                block_dim,
+                bytes_per_pixel,
+                mipmap_count,
+            )
+            .scan(start_offset, |offset, size| {
+                let mip_offset = *offset;
+                *offset += size as u64;
+                Some(mip_offset)
+            })
+            .collect();
+            (data, mipmap_offsets, 0)
+        }
+        TileMode::Unknown(_) => {
+            return Err(CreateBntxError::UnsupportedTileMode(options.tile_mode))
+        }
+    };
+
+    let brti = Brti {
+        size: TEMP_OFFSET,
+        size2: TEMP_OFFSET as u64,
+        flags: 1,
+        texture_dimension: view_dimension.texture_dimension(),
+        tile_mode: options.tile_mode,
+        swizzle: 0,
+        mipmap_count: mipmap_count as u16,
+        multi_sample_count: 1,
+        image_format: format,
+        unk2: 32,
+        width,
+        height,
+        depth,
+        layer_count,
+        block_height_log2,
+        unk4: [65543, 0, 0, 0, 0, 0],
+        image_size: data.len() as u32,
+        align: align as u32,
+        comp_sel: ChannelSelector::default(),
+        texture_view_dimension: view_dimension,
+        name_addr: TEMP_OFFSET as u64,
+        name: name.to_string(),
+        parent_addr: 32,
+        mipmaps: Mipmaps { mipmap_offsets },
+        unk5: 0,
+        unk6: [0; 256],
+        unk7: [0; 256],
+        unk: [0; 4],
+    };
+
+    Ok((brti, data))
 }
 
-// TODO: Don't hard code these values.
-const BRTD_SECTION_START: usize = 0xFF0;
-const SIZE_OF_BRTD: usize = 0x10;
-const START_OF_TEXTURE_DATA: usize = BRTD_SECTION_START + SIZE_OF_BRTD;
+// The writer relocates the image data, so any initial position works.
+const START_OF_TEXTURE_DATA: u64 = 0x1000;
 
+#[allow(clippy::too_many_arguments)]
 fn calculate_mipmap_offsets(
+    start_offset: u64,
     mipmap_count: u32,
     width: u32,
     block_dim: BlockDim,
@@ -286,7 +709,7 @@ fn calculate_mipmap_offsets(
 
     let mut mipmap_offset = 0;
     for mip in 0..mipmap_count {
-        mipmap_offsets.push(START_OF_TEXTURE_DATA as u64 + mipmap_offset as u64);
+        mipmap_offsets.push(start_offset + mipmap_offset as u64);
 
         let mip_width = div_round_up((width >> mip).max(1), block_dim.width.get());
         let mip_height = div_round_up((height >> mip).max(1), block_dim.height.get());
@@ -311,13 +734,84 @@ impl TryFrom<SurfaceFormat> for image_dds::ImageFormat {
     fn try_from(value: SurfaceFormat) -> Result<Self, Self::Error> {
         // TODO: Add support to image_dds for remaining formats
         match value {
+            SurfaceFormat::R4G4Unorm => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
             SurfaceFormat::R8Unorm => Ok(Self::R8Unorm),
-            SurfaceFormat::Unk1 => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R8Snorm => Ok(Self::R8Snorm),
+            SurfaceFormat::R8Uint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R8Sint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            // Packed formats list channels starting from the least significant bits.
+            // image_dds only has Bgra4Unorm and Bgr5A1Unorm with blue in the lowest bits.
+            SurfaceFormat::R4G4B4A4Unorm => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::A4B4G4R4Unorm => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::R5G5B5A1Unorm => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::A1B5G5R5Unorm => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::R5G6B5Unorm => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::B5G6R5Unorm => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R8G8Unorm => Ok(Self::Rg8Unorm),
+            SurfaceFormat::R8G8Snorm => Ok(Self::Rg8Snorm),
+            SurfaceFormat::R8G8Uint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R8G8Sint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R16Unorm => Ok(Self::R16Unorm),
+            SurfaceFormat::R16Snorm => Ok(Self::R16Snorm),
+            SurfaceFormat::R16Uint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R16Sint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R16Float => Ok(Self::R16Float),
             SurfaceFormat::R8G8B8A8Unorm => Ok(Self::Rgba8Unorm),
+            SurfaceFormat::R8G8B8A8Snorm => Ok(Self::Rgba8Snorm),
+            SurfaceFormat::R8G8B8A8Uint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R8G8B8A8Sint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
             SurfaceFormat::R8G8B8A8Srgb => Ok(Self::Rgba8UnormSrgb),
             SurfaceFormat::B8G8R8A8Unorm => Ok(Self::Bgra8Unorm),
             SurfaceFormat::B8G8R8A8Srgb => Ok(Self::Bgra8UnormSrgb),
+            SurfaceFormat::R10G10B10A2Unorm => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::R10G10B10A2Uint => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
             SurfaceFormat::R11G11B10 => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R16G16Unorm => Ok(Self::Rg16Unorm),
+            SurfaceFormat::R16G16Snorm => Ok(Self::Rg16Snorm),
+            SurfaceFormat::R16G16Uint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R16G16Sint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R16G16Float => Ok(Self::Rg16Float),
+            SurfaceFormat::R32Uint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R32Sint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R32Float => Ok(Self::R32Float),
+            SurfaceFormat::R16G16B16A16Unorm => Ok(Self::Rgba16Unorm),
+            SurfaceFormat::R16G16B16A16Snorm => Ok(Self::Rgba16Snorm),
+            SurfaceFormat::R16G16B16A16Uint => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::R16G16B16A16Sint => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::R16G16B16A16Float => Ok(Self::Rgba16Float),
+            SurfaceFormat::R32G32Uint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R32G32Sint => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::R32G32Float => Ok(Self::Rg32Float),
+            SurfaceFormat::R32G32B32Uint => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::R32G32B32Sint => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::R32G32B32Float => Ok(Self::Rgb32Float),
+            SurfaceFormat::R32G32B32A32Uint => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::R32G32B32A32Sint => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::R32G32B32A32Float => Ok(Self::Rgba32Float),
             SurfaceFormat::BC1Unorm => Ok(Self::BC1RgbaUnorm),
             SurfaceFormat::BC1Srgb => Ok(Self::BC1RgbaUnormSrgb),
             SurfaceFormat::BC2Unorm => Ok(Self::BC2RgbaUnorm),
@@ -332,6 +826,54 @@ impl TryFrom<SurfaceFormat> for image_dds::ImageFormat {
             SurfaceFormat::BC6Ufloat => Ok(Self::BC6hRgbUfloat),
             SurfaceFormat::BC7Unorm => Ok(Self::BC7RgbaUnorm),
             SurfaceFormat::BC7Srgb => Ok(Self::BC7RgbaUnormSrgb),
+            SurfaceFormat::Astc4x4Unorm => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc4x4Srgb => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc5x4Unorm => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc5x4Srgb => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc5x5Unorm => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc5x5Srgb => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc6x5Unorm => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc6x5Srgb => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc6x6Unorm => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc6x6Srgb => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc8x5Unorm => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc8x5Srgb => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc8x6Unorm => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc8x6Srgb => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc8x8Unorm => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc8x8Srgb => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc10x5Unorm => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::Astc10x5Srgb => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc10x6Unorm => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::Astc10x6Srgb => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc10x8Unorm => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::Astc10x8Srgb => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
+            SurfaceFormat::Astc10x10Unorm => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::Astc10x10Srgb => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::Astc12x10Unorm => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::Astc12x10Srgb => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::Astc12x12Unorm => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::Astc12x12Srgb => {
+                Err(CreateSurfaceError::UnsupportedSurfaceFormat(value))
+            }
+            SurfaceFormat::B5G5R5A1Unorm => Ok(Self::Bgr5A1Unorm),
+            SurfaceFormat::Unknown(_) => Err(CreateSurfaceError::UnsupportedSurfaceFormat(value)),
         }
     }
 }
@@ -342,19 +884,32 @@ impl TryFrom<image_dds::ImageFormat> for SurfaceFormat {
     fn try_from(value: image_dds::ImageFormat) -> Result<Self, Self::Error> {
         match value {
             image_dds::ImageFormat::R8Unorm => Ok(Self::R8Unorm),
+            image_dds::ImageFormat::R8Snorm => Ok(Self::R8Snorm),
+            image_dds::ImageFormat::Rg8Unorm => Ok(Self::R8G8Unorm),
+            image_dds::ImageFormat::Rg8Snorm => Ok(Self::R8G8Snorm),
+            image_dds::ImageFormat::R16Unorm => Ok(Self::R16Unorm),
+            image_dds::ImageFormat::R16Snorm => Ok(Self::R16Snorm),
+            image_dds::ImageFormat::R16Float => Ok(Self::R16Float),
             image_dds::ImageFormat::Rgba8Unorm => Ok(Self::R8G8B8A8Unorm),
+            image_dds::ImageFormat::Rgba8Snorm => Ok(Self::R8G8B8A8Snorm),
             image_dds::ImageFormat::Rgba8UnormSrgb => Ok(Self::R8G8B8A8Srgb),
-            image_dds::ImageFormat::Rgba16Float => {
-                Err(CreateBntxError::UnsupportedImageFormat(value))
-            }
-            image_dds::ImageFormat::Rgba32Float => {
-                Err(CreateBntxError::UnsupportedImageFormat(value))
-            }
             image_dds::ImageFormat::Bgra8Unorm => Ok(Self::B8G8R8A8Unorm),
             image_dds::ImageFormat::Bgra8UnormSrgb => Ok(Self::B8G8R8A8Srgb),
+            image_dds::ImageFormat::Bgr5A1Unorm => Ok(Self::B5G5R5A1Unorm),
+            // There is no B4G4R4A4 format with blue in the lowest bits.
             image_dds::ImageFormat::Bgra4Unorm => {
                 Err(CreateBntxError::UnsupportedImageFormat(value))
             }
+            image_dds::ImageFormat::Rg16Unorm => Ok(Self::R16G16Unorm),
+            image_dds::ImageFormat::Rg16Snorm => Ok(Self::R16G16Snorm),
+            image_dds::ImageFormat::Rg16Float => Ok(Self::R16G16Float),
+            image_dds::ImageFormat::R32Float => Ok(Self::R32Float),
+            image_dds::ImageFormat::Rgba16Unorm => Ok(Self::R16G16B16A16Unorm),
+            image_dds::ImageFormat::Rgba16Snorm => Ok(Self::R16G16B16A16Snorm),
+            image_dds::ImageFormat::Rgba16Float => Ok(Self::R16G16B16A16Float),
+            image_dds::ImageFormat::Rg32Float => Ok(Self::R32G32Float),
+            image_dds::ImageFormat::Rgb32Float => Ok(Self::R32G32B32Float),
+            image_dds::ImageFormat::Rgba32Float => Ok(Self::R32G32B32A32Float),
             image_dds::ImageFormat::BC1RgbaUnorm => Ok(Self::BC1Unorm),
             image_dds::ImageFormat::BC1RgbaUnormSrgb => Ok(Self::BC1Srgb),
             image_dds::ImageFormat::BC2RgbaUnorm => Ok(Self::BC2Unorm),
@@ -365,8 +920,8 @@ impl TryFrom<image_dds::ImageFormat> for SurfaceFormat {
             image_dds::ImageFormat::BC4RSnorm => Ok(Self::BC4Snorm),
             image_dds::ImageFormat::BC5RgUnorm => Ok(Self::BC5Unorm),
             image_dds::ImageFormat::BC5RgSnorm => Ok(Self::BC5Snorm),
-            image_dds::ImageFormat::BC6hRgbUfloat => Ok(Self::BC6Ufloat),
             image_dds::ImageFormat::BC6hRgbSfloat => Ok(Self::BC6Sfloat),
+            image_dds::ImageFormat::BC6hRgbUfloat => Ok(Self::BC6Ufloat),
             image_dds::ImageFormat::BC7RgbaUnorm => Ok(Self::BC7Unorm),
             image_dds::ImageFormat::BC7RgbaUnormSrgb => Ok(Self::BC7Srgb),
             _ => Err(CreateBntxError::UnsupportedImageFormat(value)),
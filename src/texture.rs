@@ -0,0 +1,435 @@
+use std::convert::TryFrom;
+
+use tegra_swizzle::{
+    block_height_mip0, div_round_up, mip_block_height,
+    surface::BlockDim,
+    swizzle::{deswizzle_block_linear, deswizzled_mip_size, swizzled_mip_size},
+    BlockHeight, SwizzleError,
+};
+
+use crate::{
+    linear, surface::CreateSurfaceError, Brtd, Brti, SurfaceFormat, TextureViewDimension, TileMode,
+};
+
+const GOB_WIDTH_IN_BYTES: u32 = 64;
+const GOB_HEIGHT_IN_BYTES: u32 = 8;
+const GOB_SIZE_IN_BYTES: usize = 512;
+
+/// The deswizzled image data for a single array layer and mipmap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mip {
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub data: Vec<u8>,
+}
+
+/// A view of a single texture and its image data in a [Bntx](crate::Bntx).
+#[derive(Debug, Clone, Copy)]
+pub struct Texture<'a> {
+    brti: &'a Brti,
+    brtd: &'a Brtd,
+}
+
+impl<'a> Texture<'a> {
+    pub(crate) fn new(brti: &'a Brti, brtd: &'a Brtd) -> Self {
+        Self { brti, brtd }
+    }
+
+    /// The texture information header for this texture.
+    pub fn brti(&self) -> &'a Brti {
+        self.brti
+    }
+
+    pub fn name(&self) -> &'a str {
+        &self.brti.name
+    }
+
+    pub fn width(&self) -> u32 {
+        self.brti.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.brti.height
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.brti.depth
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        self.brti.layer_count
+    }
+
+    pub fn mipmap_count(&self) -> u32 {
+        self.brti.mipmap_count as u32
+    }
+
+    pub fn image_format(&self) -> SurfaceFormat {
+        self.brti.image_format
+    }
+
+    pub fn view_dimension(&self) -> TextureViewDimension {
+        self.brti.texture_view_dimension
+    }
+
+    pub fn tile_mode(&self) -> TileMode {
+        self.brti.tile_mode
+    }
+
+    /// The swizzled image data for all layers and mipmaps.
+    ///
+    /// This is the region of [Brtd::image_data] starting at the first mipmap offset
+    /// with a length of [Brti::image_size].
+    pub fn image_data(&self) -> Result<&'a [u8], CreateSurfaceError> {
+        let offset = *self
+            .brti
+            .mipmaps
+            .mipmap_offsets
+            .first()
+            .ok_or(CreateSurfaceError::MissingMipmapOffset(0))?;
+        let invalid_offset = || CreateSurfaceError::InvalidMipmapOffset { level: 0, offset };
+
+        let start = offset
+            .checked_sub(self.brtd.data_offset)
+            .and_then(|start| usize::try_from(start).ok())
+            .filter(|start| *start <= self.brtd.image_data.len())
+            .ok_or_else(invalid_offset)?;
+        let image_size = self.brti.image_size;
+        let end = start
+            .checked_add(image_size as usize)
+            .filter(|end| *end <= self.brtd.image_data.len())
+            .ok_or(CreateSurfaceError::InvalidImageSize { offset, image_size })?;
+        Ok(&self.brtd.image_data[start..end])
+    }
+
+    /// The block height for the first mipmap from [Brti::block_height_log2].
+    /// This is not used for deswizzling 3D textures.
+    pub fn block_height(&self) -> Result<BlockHeight, CreateSurfaceError> {
+        let log2 = self.brti.block_height_log2;
+        1u32.checked_shl(log2)
+            .and_then(BlockHeight::new)
+            .ok_or(CreateSurfaceError::InvalidBlockHeight(log2))
+    }
+
+    /// The block height for the first mipmap inferred from the height and format.
+    /// This is the default used for new textures.
+    ///
+    /// 3D textures always use [BlockHeight::One].
+    pub fn inferred_block_height(&self) -> Result<BlockHeight, CreateSurfaceError> {
+        let format = self.image_format();
+        let block_dim = format
+            .block_dim()
+            .ok_or(CreateSurfaceError::UnsupportedSurfaceFormat(format))?;
+        if self.depth() > 1 {
+            return Ok(BlockHeight::One);
+        }
+        Ok(block_height_mip0(div_round_up(
+            self.height(),
+            block_dim.height.get(),
+        )))
+    }
+
+    /// The deswizzled image data for all layers and mipmaps.
+    ///
+    /// Each mipmap is located using the offsets from [Brti::mipmaps].
+    /// Block linear textures use the block height from the header
+    /// except for 3D textures, which always use [BlockHeight::One].
+    /// See [Texture::deswizzled_data_strict] for also checking the block height.
+    pub fn deswizzled_data(&self) -> Result<Vec<u8>, CreateSurfaceError> {
+        self.deswizzle(Self::block_height)
+    }
+
+    /// The deswizzled image data for all layers and mipmaps.
+    ///
+    /// Returns an error if the block height from the header differs from the inferred block height.
+    /// This includes 3D textures with a header block height other than [BlockHeight::One]
+    /// that [Texture::deswizzled_data] ignores.
+    pub fn deswizzled_data_strict(&self) -> Result<Vec<u8>, CreateSurfaceError> {
+        self.deswizzle(Self::checked_block_height)
+    }
+
+    /// The deswizzled image data for a single array layer and mipmap.
+    ///
+    /// Only the data for the mipmap is deswizzled using the offsets from [Brti::mipmaps].
+    pub fn mip(&self, layer: u32, level: u32) -> Result<Mip, CreateSurfaceError> {
+        self.deswizzle_mip(layer, level, Self::block_height)
+    }
+
+    fn deswizzle_mip(
+        &self,
+        layer: u32,
+        level: u32,
+        block_height: fn(&Self) -> Result<BlockHeight, CreateSurfaceError>,
+    ) -> Result<Mip, CreateSurfaceError> {
+        let out_of_range = || CreateSurfaceError::MipOutOfRange { layer, level };
+        if layer >= self.layer_count() || level >= self.mipmap_count() {
+            return Err(out_of_range());
+        }
+
+        let format = self.image_format();
+        let unsupported = || CreateSurfaceError::UnsupportedSurfaceFormat(format);
+        let block_dim = format.block_dim().ok_or_else(unsupported)?;
+        let bytes_per_pixel = format.bytes_per_pixel().ok_or_else(unsupported)?;
+        self.validate_dimensions(block_dim, bytes_per_pixel)?;
+
+        let width = (self.width() >> level).max(1);
+        let height = (self.height() >> level).max(1);
+        let depth = (self.depth() >> level).max(1);
+        let width_blocks = div_round_up(width, block_dim.width.get());
+        let height_blocks = div_round_up(height, block_dim.height.get());
+        let depth_blocks = div_round_up(depth, block_dim.depth.get());
+
+        let data = match self.brti.tile_mode {
+            // The block depth for 3D mipmaps depends on the depth of the first mipmap.
+            TileMode::BlockLinear if self.depth() > 1 => {
+                let data = self.deswizzle(block_height)?;
+                let layer_size = data.len() / self.layer_count() as usize;
+                let offset = layer_size * layer as usize
+                    + (0..level)
+                        .map(|mip| self.deswizzled_mip_size(mip, block_dim, bytes_per_pixel))
+                        .sum::<usize>();
+                let size =
+                    deswizzled_mip_size(width_blocks, height_blocks, depth_blocks, bytes_per_pixel);
+                data.get(offset..offset + size)
+                    .ok_or_else(out_of_range)?
+                    .to_vec()
+            }
+            TileMode::BlockLinear => {
+                let source = self.mip_image_data(layer, level)?;
+                let block_height = mip_block_height(height_blocks, block_height(self)?);
+
+                // tegra_swizzle allocates the output before checking the input size.
+                let expected_size = swizzled_mip_size(
+                    width_blocks,
+                    height_blocks,
+                    depth_blocks,
+                    block_height,
+                    bytes_per_pixel,
+                );
+                if source.len() < expected_size {
+                    return Err(SwizzleError::NotEnoughData {
+                        expected_size,
+                        actual_size: source.len(),
+                    }
+                    .into());
+                }
+
+                deswizzle_block_linear(
+                    width_blocks,
+                    height_blocks,
+                    depth_blocks,
+                    source,
+                    block_height,
+                    bytes_per_pixel,
+                )?
+            }
+            TileMode::Linear => linear::deswizzle(
+                width,
+                height,
+                depth,
+                self.mip_image_data(layer, level)?,
+                block_dim,
+                bytes_per_pixel,
+                1,
+                1,
+            )?,
+            TileMode::Unknown(_) => {
+                return Err(CreateSurfaceError::UnsupportedTileMode(self.brti.tile_mode))
+            }
+        };
+
+        Ok(Mip {
+            width,
+            height,
+            depth,
+            data,
+        })
+    }
+
+    // Check dimensions to prevent overflow in tegra_swizzle.
+    // tegra_swizzle calculates sizes with unchecked math before checking the data length.
+    fn validate_dimensions(
+        &self,
+        block_dim: BlockDim,
+        bytes_per_pixel: u32,
+    ) -> Result<(), CreateSurfaceError> {
+        let (width, height, depth) = (self.width(), self.height(), self.depth());
+        let mipmap_count = self.mipmap_count();
+        let invalid = || SwizzleError::InvalidSurface {
+            width,
+            height,
+            depth,
+            bytes_per_pixel,
+            mipmap_count,
+        };
+        if width == 0 || height == 0 || depth == 0 || self.layer_count() == 0 || mipmap_count == 0 {
+            return Err(invalid().into());
+        }
+
+        // The first mipmap is the largest, so checking its size also checks later mipmaps.
+        // Use the largest block height and block depth to not depend on the header.
+        let width_blocks = checked_div_round_up(width, block_dim.width.get());
+        let height_blocks = checked_div_round_up(height, block_dim.height.get());
+        let depth_blocks = checked_div_round_up(depth, block_dim.depth.get());
+        let block_height = BlockHeight::ThirtyTwo as u32;
+        let deswizzled_size = width_blocks
+            .zip(height_blocks)
+            .zip(depth_blocks)
+            .and_then(|((w, h), d)| w.checked_mul(h)?.checked_mul(d))
+            .and_then(|u| u.checked_mul(bytes_per_pixel));
+        let swizzled_size = width_blocks
+            .and_then(|w| w.checked_mul(bytes_per_pixel))
+            .and_then(|w| checked_div_round_up(w, GOB_WIDTH_IN_BYTES))
+            .zip(
+                height_blocks
+                    .and_then(|h| checked_div_round_up(h, block_height * GOB_HEIGHT_IN_BYTES))
+                    .and_then(|h| h.checked_mul(block_height)),
+            )
+            .zip(
+                depth_blocks
+                    .filter(|d| d.checked_add(d / 2).is_some())
+                    .and_then(|d| d.checked_next_multiple_of(16)),
+            )
+            .and_then(|((w, h), d)| {
+                (w as usize)
+                    .checked_mul(h as usize)?
+                    .checked_mul(d as usize)?
+                    .checked_mul(GOB_SIZE_IN_BYTES)
+            });
+        // Each mipmap is at most the size of the first mipmap.
+        // Include an extra mipmap for the padding between layers.
+        let surface_size = swizzled_size
+            .and_then(|s| s.checked_mul(mipmap_count as usize + 1))
+            .and_then(|s| s.checked_mul(self.layer_count() as usize));
+
+        if deswizzled_size.is_none() || surface_size.is_none() || mipmap_count > u32::BITS {
+            return Err(invalid().into());
+        }
+        Ok(())
+    }
+
+    // The swizzled data from the start of the mipmap to the end of its layer.
+    // Layers are assumed to evenly divide the image size.
+    fn mip_image_data(&self, layer: u32, level: u32) -> Result<&'a [u8], CreateSurfaceError> {
+        let offsets = &self.brti.mipmaps.mipmap_offsets;
+        let offset = *offsets
+            .get(level as usize)
+            .ok_or(CreateSurfaceError::MissingMipmapOffset(level))?;
+
+        let layer_size = (self.brti.image_size / self.layer_count()) as u64;
+        let mip_offset = offset
+            .checked_sub(offsets[0])
+            .filter(|o| *o < layer_size)
+            .ok_or(CreateSurfaceError::InvalidMipmapOffset { level, offset })?;
+
+        let layer_start = layer_size * layer as u64;
+        let start = (layer_start + mip_offset) as usize;
+        let end = (layer_start + layer_size) as usize;
+        let data = self.image_data()?;
+        Ok(data.get(start..end.min(data.len())).unwrap_or_default())
+    }
+
+    // tegra_swizzle deswizzles 3D mipmaps packed one after another from the first offset.
+    // 3D textures always use a block height of one.
+    fn validate_mipmap_offsets_3d(
+        &self,
+        block_dim: BlockDim,
+        bytes_per_pixel: u32,
+    ) -> Result<(), CreateSurfaceError> {
+        let offsets = &self.brti.mipmaps.mipmap_offsets;
+        let mut expected = *offsets
+            .first()
+            .ok_or(CreateSurfaceError::MissingMipmapOffset(0))?;
+        for level in 0..self.mipmap_count() {
+            let offset = *offsets
+                .get(level as usize)
+                .ok_or(CreateSurfaceError::MissingMipmapOffset(level))?;
+            if offset != expected {
+                return Err(CreateSurfaceError::MipmapOffsetMismatch {
+                    level,
+                    offset,
+                    expected,
+                });
+            }
+
+            let height = div_round_up((self.height() >> level).max(1), block_dim.height.get());
+            let size = swizzled_mip_size(
+                div_round_up((self.width() >> level).max(1), block_dim.width.get()),
+                height,
+                div_round_up((self.depth() >> level).max(1), block_dim.depth.get()),
+                mip_block_height(height, BlockHeight::One),
+                bytes_per_pixel,
+            );
+            expected = expected.saturating_add(size as u64);
+        }
+        Ok(())
+    }
+
+    fn deswizzled_mip_size(&self, level: u32, block_dim: BlockDim, bytes_per_pixel: u32) -> usize {
+        deswizzled_mip_size(
+            div_round_up((self.width() >> level).max(1), block_dim.width.get()),
+            div_round_up((self.height() >> level).max(1), block_dim.height.get()),
+            div_round_up((self.depth() >> level).max(1), block_dim.depth.get()),
+            bytes_per_pixel,
+        )
+    }
+
+    fn checked_block_height(&self) -> Result<BlockHeight, CreateSurfaceError> {
+        let block_height = self.block_height()?;
+        let inferred = self.inferred_block_height()?;
+        if block_height != inferred {
+            return Err(CreateSurfaceError::BlockHeightMismatch {
+                block_height,
+                inferred,
+            });
+        }
+        Ok(block_height)
+    }
+
+    fn deswizzle(
+        &self,
+        block_height: fn(&Self) -> Result<BlockHeight, CreateSurfaceError>,
+    ) -> Result<Vec<u8>, CreateSurfaceError> {
+        let format = self.image_format();
+        let unsupported = || CreateSurfaceError::UnsupportedSurfaceFormat(format);
+        let block_dim = format.block_dim().ok_or_else(unsupported)?;
+        let bytes_per_pixel = format.bytes_per_pixel().ok_or_else(unsupported)?;
+        self.validate_dimensions(block_dim, bytes_per_pixel)?;
+
+        match self.brti.tile_mode {
+            TileMode::BlockLinear if self.depth() > 1 => {
+                self.validate_mipmap_offsets_3d(block_dim, bytes_per_pixel)?;
+
+                tegra_swizzle::surface::deswizzle_surface(
+                    self.width(),
+                    self.height(),
+                    self.depth(),
+                    self.image_data()?,
+                    block_dim,
+                    Some(block_height(self)?),
+                    bytes_per_pixel,
+                    self.mipmap_count(),
+                    self.layer_count(),
+                )
+                .map_err(Into::into)
+            }
+            TileMode::BlockLinear | TileMode::Linear => {
+                let mut data = Vec::new();
+                for layer in 0..self.layer_count() {
+                    for level in 0..self.mipmap_count() {
+                        data.extend(self.deswizzle_mip(layer, level, block_height)?.data);
+                    }
+                }
+                Ok(data)
+            }
+            TileMode::Unknown(_) => {
+                Err(CreateSurfaceError::UnsupportedTileMode(self.brti.tile_mode))
+            }
+        }
+    }
+}
+
+fn checked_div_round_up(x: u32, d: u32) -> Option<u32> {
+    x.checked_add(d - 1).map(|x| x / d)
+}